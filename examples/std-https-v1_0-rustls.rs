@@ -8,10 +8,9 @@ use std::{
 };
 
 use io_stream::{
-    coroutines::{Read, Write},
-    runtimes::std::handle,
+    coroutines::{Flush, ReadUntil, WriteAll},
+    runtimes::std::run,
 };
-use memchr::memmem;
 use rustls::{ClientConfig, ClientConnection, StreamOwned};
 use rustls_platform_verifier::ConfigVerifierExt;
 use url::Url;
@@ -28,39 +27,10 @@ fn main() {
 
     let request = format!("GET {} HTTP/1.0\r\n\r\n", url.path());
 
-    let mut arg = None;
-    let mut write = Write::new(request.into_bytes());
+    run(&mut stream, WriteAll::new(request.into_bytes())).unwrap();
+    run(&mut stream, Flush::new()).unwrap();
 
-    while let Err(io) = write.resume(arg) {
-        arg = Some(handle(&mut stream, io).unwrap());
-    }
-
-    let mut response = Vec::new();
-
-    loop {
-        let mut arg = None;
-        let mut read = Read::new();
-
-        let output = loop {
-            match read.resume(arg) {
-                Ok(output) => break output,
-                Err(io) => arg = Some(handle(&mut stream, io).unwrap()),
-            }
-        };
-
-        let bytes = output.bytes();
-
-        match memmem::find(bytes, &[b'\r', b'\n', b'\r', b'\n']) {
-            None => {
-                response.extend(bytes);
-                continue;
-            }
-            Some(n) => {
-                response.extend(&bytes[..n]);
-                break;
-            }
-        }
-    }
+    let response = run(&mut stream, ReadUntil::new(*b"\r\n\r\n")).unwrap();
 
     println!("----------------");
     println!("{}", String::from_utf8_lossy(&response));
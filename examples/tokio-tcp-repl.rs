@@ -3,8 +3,8 @@
 use std::env;
 
 use io_stream::{
-    coroutines::{Read, Write},
-    runtimes::tokio::handle,
+    coroutines::{BufferedReader, LineEnding, ReadLine, WriteAll},
+    runtimes::tokio::run,
 };
 use tokio::{
     io::{stdin, stdout, AsyncBufReadExt, AsyncWriteExt, BufReader, Stdout},
@@ -32,62 +32,47 @@ async fn main() {
 
     let mut tcp = TcpStream::connect((host.as_str(), port)).await.unwrap();
 
-    stdout.write(b"\nReceived greeting:\n").await.unwrap();
+    stdout.write_all(b"\nReceived greeting:\n").await.unwrap();
 
-    let mut arg = None;
-    let mut read = Read::new();
+    let mut reader = BufferedReader::new();
+    print_lines(&mut stdout, &mut tcp, &mut reader).await;
 
-    let greeting = loop {
-        match read.resume(arg) {
-            Ok(output) => break output,
-            Err(io) => arg = Some(handle(&mut tcp, io).await.unwrap()),
-        }
-    };
+    loop {
+        stdout.write_all(b"\n").await.unwrap();
 
-    let mut lines = greeting.bytes().lines();
-    while let Ok(Some(line)) = lines.next_line().await {
-        stdout
-            .write(format!("S: {line}\n").as_bytes())
+        let mut data = prompt(&mut stdout, "C:").await;
+        data.push_str("\r\n");
+
+        run(&mut tcp, WriteAll::new(data.into_bytes()))
             .await
             .unwrap();
+
+        print_lines(&mut stdout, &mut tcp, &mut reader).await;
     }
+}
 
+/// Prints the next line received from the server, as well as all the
+/// following lines already received.
+async fn print_lines(stdout: &mut Stdout, tcp: &mut TcpStream, reader: &mut BufferedReader) {
     loop {
-        stdout.write(b"\n").await.unwrap();
+        let read = ReadLine::with_reader(&mut *reader).line_ending(LineEnding::Crlf);
+        let line = run(&mut *tcp, read).await.unwrap();
+        let line = String::from_utf8_lossy(&line);
 
-        let mut data = prompt(&mut stdout, "C:").await;
-        data.push_str("\r\n");
-
-        let mut arg = None;
-        let mut write = Write::new(data.into_bytes());
-
-        while let Err(io) = write.resume(arg) {
-            arg = Some(handle(&mut tcp, io).await.unwrap());
-        }
+        stdout
+            .write_all(format!("S: {line}\n").as_bytes())
+            .await
+            .unwrap();
 
-        let mut arg = None;
-        let mut read = Read::new();
-
-        let response = loop {
-            match read.resume(arg) {
-                Ok(output) => break output,
-                Err(io) => arg = Some(handle(&mut tcp, io).await.unwrap()),
-            }
-        };
-
-        let mut lines = response.bytes().lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            stdout
-                .write(format!("S: {line}\n").as_bytes())
-                .await
-                .unwrap();
+        if reader.is_empty() {
+            break;
         }
     }
 }
 
 async fn prompt(stdout: &mut Stdout, message: &str) -> String {
     stdout
-        .write(format!("{message} ").as_bytes())
+        .write_all(format!("{message} ").as_bytes())
         .await
         .unwrap();
 
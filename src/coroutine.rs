@@ -0,0 +1,93 @@
+//! Module dedicated to the [`Coroutine`] trait.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use crate::{
+    coroutines::{AndThen, Map, Then, Timeout},
+    Io,
+};
+
+/// The I/O-free coroutine trait, shared by all [coroutines].
+///
+/// A coroutine is resumed with the response of the previous [`Io`]
+/// request it emitted (or `None` the first time), until it returns
+/// its output.
+///
+/// [coroutines]: crate::coroutines
+pub trait Coroutine {
+    /// The type of value produced by the coroutine once terminated.
+    type Output;
+
+    /// Makes the coroutine progress.
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io>;
+
+    /// Maps the output of the coroutine with the given function.
+    fn map<F, T>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+        F: FnOnce(Self::Output) -> T,
+    {
+        Map::new(self, f)
+    }
+
+    /// Chains the coroutine built by the given function from the
+    /// output of this coroutine.
+    ///
+    /// Coroutines reading from the stream should share the same
+    /// [`BufferedReader`], otherwise bytes read ahead by the first
+    /// coroutine are lost for the second one.
+    ///
+    /// [`BufferedReader`]: crate::coroutines::BufferedReader
+    fn and_then<F, D>(self, f: F) -> AndThen<Self, F, D>
+    where
+        Self: Sized,
+        F: FnOnce(Self::Output) -> D,
+        D: Coroutine,
+    {
+        AndThen::new(self, f)
+    }
+
+    /// Resumes the given coroutine once this coroutine terminated,
+    /// discarding its output.
+    ///
+    /// This is typically used to send a request, then to read its
+    /// response.
+    fn then<D>(self, next: D) -> Then<Self, D>
+    where
+        Self: Sized,
+        D: Coroutine,
+    {
+        Then::new(self, next)
+    }
+
+    /// Bounds the coroutine with the given duration, shared by all the
+    /// requests it emits.
+    ///
+    /// The coroutine emits [`Error::TimedOut`] if the runtime did not
+    /// process its requests in time. Not all runtimes support timeouts.
+    ///
+    /// [`Error::TimedOut`]: crate::Error::TimedOut
+    fn timeout(self, timeout: Duration) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        Timeout::new(self, timeout)
+    }
+}
+
+impl<C: Coroutine + ?Sized> Coroutine for &mut C {
+    type Output = C::Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        C::resume(self, arg)
+    }
+}
+
+impl<C: Coroutine + ?Sized> Coroutine for Box<C> {
+    type Output = C::Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        C::resume(self, arg)
+    }
+}
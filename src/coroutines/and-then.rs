@@ -0,0 +1,71 @@
+//! Module dedicated to the [`AndThen`] coroutine combinator.
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+/// Coroutine combinator chaining a coroutine built from the output
+/// of another one.
+///
+/// See [`Coroutine::and_then`].
+#[derive(Debug)]
+pub struct AndThen<C, F, D> {
+    first: C,
+    f: Option<F>,
+    second: Option<D>,
+}
+
+impl<C, F, D> AndThen<C, F, D> {
+    /// Creates a new combinator resuming the given coroutine, then
+    /// the coroutine built by the given function from its output.
+    pub fn new(coroutine: C, f: F) -> Self {
+        Self {
+            first: coroutine,
+            f: Some(f),
+            second: None,
+        }
+    }
+}
+
+impl<C: Coroutine, F: FnOnce(C::Output) -> D, D: Coroutine> Coroutine for AndThen<C, F, D> {
+    type Output = D::Output;
+
+    fn resume(&mut self, mut arg: Option<Io>) -> Result<Self::Output, Io> {
+        loop {
+            if let Some(second) = &mut self.second {
+                break second.resume(arg);
+            }
+
+            let output = self.first.resume(arg.take())?;
+
+            let Some(f) = self.f.take() else {
+                debug!("and-then resumed after termination");
+                break Err(Error::BufferNotReady.into());
+            };
+
+            debug!("first coroutine terminated, chaining the second one");
+            self.second = Some(f(output));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{ReadExact, ReadLine},
+        runtimes::mock::{run, MockStream},
+        Coroutine,
+    };
+
+    #[test]
+    fn length_prefixed() {
+        let mut stream = MockStream::new().read("5\r\n").read("hello");
+
+        let read = ReadLine::new().and_then(|line| {
+            let len = core::str::from_utf8(&line).unwrap().parse().unwrap();
+            ReadExact::with_capacity(8, len)
+        });
+
+        assert_eq!(run(&mut stream, read), Ok(b"hello".to_vec()));
+    }
+}
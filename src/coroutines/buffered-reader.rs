@@ -0,0 +1,165 @@
+//! Module dedicated to the [`BufferedReader`] I/O-free coroutine.
+
+use alloc::vec::Vec;
+
+use log::debug;
+
+use crate::{Coroutine, Io};
+
+use super::Read;
+
+/// I/O-free coroutine for reading bytes into a persistent buffer.
+///
+/// Like [`std::io::BufReader`], bytes read from the stream but not
+/// consumed are kept in an inner buffer. This buffer can then be
+/// shared across coroutines (see `with_reader` constructors), so that
+/// bytes read beyond what a coroutine needs are not lost.
+#[derive(Debug)]
+pub struct BufferedReader {
+    read: Read,
+    capacity: usize,
+    buffer: Vec<u8>,
+}
+
+impl BufferedReader {
+    /// Creates a new buffered reader with a read buffer capacity of
+    /// 1024.
+    ///
+    /// See [`BufferedReader::with_capacity`] for a different buffer
+    /// capacity.
+    pub fn new() -> Self {
+        Self::with_capacity(1024)
+    }
+
+    /// Creates a new buffered reader with the given read buffer
+    /// capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            read: Read::with_capacity(capacity),
+            capacity,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the bytes read but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns `true` if there is no byte left to consume.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Removes and returns at most `n` bytes from the start of the
+    /// buffer.
+    pub fn consume(&mut self, n: usize) -> Vec<u8> {
+        let n = n.min(self.buffer.len());
+        debug!("consume {n} buffered bytes");
+        self.buffer.drain(..n).collect()
+    }
+
+    /// Consumes the reader and returns the bytes not consumed yet.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Makes the fill progress.
+    ///
+    /// Reads bytes from the stream and appends them to the buffer.
+    /// Returns the amount of bytes read, 0 meaning EOF.
+    pub fn fill(&mut self, arg: Option<Io>) -> Result<usize, Io> {
+        self.fill_at_most(self.capacity, arg)
+    }
+
+    /// Makes the fill progress, reading at most `max` bytes from the
+    /// stream.
+    ///
+    /// See [`BufferedReader::fill`].
+    pub fn fill_at_most(&mut self, max: usize, arg: Option<Io>) -> Result<usize, Io> {
+        let output = match self.read.resume(arg) {
+            Ok(output) => output,
+            Err(Io::Read(Err(mut buffer))) => {
+                buffer.truncate(max);
+                return Err(Io::Read(Err(buffer)));
+            }
+            Err(io) => return Err(io),
+        };
+
+        let n = output.bytes_count;
+        self.buffer.extend(output.bytes());
+
+        let mut buffer = output.buffer;
+        buffer.resize(self.capacity, 0);
+        self.read.replace(buffer);
+
+        Ok(n)
+    }
+}
+
+impl Default for BufferedReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for BufferedReader {
+    type Output = usize;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        self.fill(arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{ReadExact, ReadToEnd, ReadUntil},
+        runtimes::mock::{handle, run, MockStream},
+        Io,
+    };
+
+    use super::BufferedReader;
+
+    #[test]
+    fn fill_at_most() {
+        let mut stream = MockStream::new().read("abcdef");
+        let mut buffered = BufferedReader::with_capacity(4);
+
+        let io = buffered.fill_at_most(2, None).unwrap_err();
+        assert_eq!(io, Io::Read(Err(vec![0; 2])));
+
+        let arg = handle(&mut stream, io).unwrap();
+        assert_eq!(buffered.fill_at_most(2, Some(arg)), Ok(2));
+        assert_eq!(buffered.buffer(), b"ab");
+
+        let n = run(&mut stream, &mut buffered).unwrap();
+
+        assert_eq!(n, 4);
+        assert_eq!(buffered.consume(3), b"abc");
+        assert_eq!(buffered.into_inner(), b"def");
+    }
+
+    #[test]
+    fn chain_coroutines() {
+        let mut stream = MockStream::new()
+            .read("HEAD\r\n\r\nbodytrai")
+            .read("ler")
+            .eof();
+        let mut buffered = BufferedReader::with_capacity(16);
+
+        let read = ReadUntil::with_reader(&mut buffered, *b"\r\n\r\n");
+        let head = run(&mut stream, read).unwrap();
+        assert_eq!(head, b"HEAD");
+        assert_eq!(buffered.buffer(), b"bodytrai");
+
+        let body = run(&mut stream, ReadExact::with_reader(&mut buffered, 4)).unwrap();
+        assert_eq!(body, b"body");
+        assert_eq!(buffered.buffer(), b"trai");
+
+        let trailer = run(&mut stream, ReadToEnd::with_reader(&mut buffered)).unwrap();
+        assert_eq!(trailer, b"trailer");
+        assert!(buffered.is_empty());
+        assert!(stream.is_done());
+    }
+}
@@ -0,0 +1,191 @@
+//! Module dedicated to the [`CopyBidirectional`] I/O-free coroutine.
+
+use alloc::boxed::Box;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io, SelectOutput, SelectRequest, Side};
+
+use super::Pipe;
+
+/// I/O-free coroutine for copying bytes between two streams, in both
+/// directions.
+///
+/// Bytes read from the left stream are written to the right stream,
+/// and the other way around, using requests tagged with
+/// [`Io::Select`]. Each direction has its own buffer, and progresses
+/// independently: a stream is read while bytes are being written to
+/// it. Once a stream reached EOF, the other stream is shut down. The
+/// coroutine
+/// terminates once both streams reached EOF, with the amount of bytes
+/// copied from left to right and from right to left.
+#[derive(Debug)]
+pub struct CopyBidirectional {
+    /// The copy from left to right, then from right to left.
+    copies: [Pipe; 2],
+}
+
+impl CopyBidirectional {
+    /// Creates a new coroutine to copy bytes between two streams,
+    /// with buffer capacities of 1024.
+    pub fn new() -> Self {
+        Self::with_capacity(1024)
+    }
+
+    /// Creates a new coroutine to copy bytes between two streams,
+    /// with the given buffer capacity for each direction.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            copies: [
+                Pipe::one_way(Side::Left, capacity),
+                Pipe::one_way(Side::Right, capacity),
+            ],
+        }
+    }
+
+    /// Makes both copies progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<(u64, u64), Io> {
+        if let Some(arg) = arg {
+            let Io::Select(Ok(SelectOutput { side, io, pending })) = arg else {
+                let got = Box::new(arg);
+                return Err(Error::UnexpectedIo {
+                    expected: "select",
+                    got,
+                }
+                .into());
+            };
+
+            self.restore(Io::Select(Err(pending)));
+
+            let read = matches!(*io, Io::Read(_));
+
+            if let Some(i) = self.owner(side, read) {
+                self.copies[i].respond(*io)?;
+            }
+        }
+
+        let [forward, backward] = &self.copies;
+
+        if forward.target().is_none() && backward.target().is_none() {
+            debug!("both streams reached EOF");
+            return Ok((forward.bytes_count(), backward.bytes_count()));
+        }
+
+        let mut request = SelectRequest::default();
+
+        for copy in &mut self.copies {
+            let Some(side) = copy.target() else {
+                continue;
+            };
+
+            let slot = match (side, side == copy.from()) {
+                (Side::Left, true) => &mut request.left,
+                (Side::Left, false) => &mut request.left_write,
+                (Side::Right, true) => &mut request.right,
+                (Side::Right, false) => &mut request.right_write,
+            };
+
+            *slot = copy.request().map(Box::new);
+        }
+
+        if request.is_empty() {
+            return Err(Error::BufferNotReady.into());
+        }
+
+        Err(Io::Select(Err(request)))
+    }
+
+    /// Takes back the buffers of the given request, given back
+    /// untouched by the runtime.
+    ///
+    /// The request can be the select request emitted by the
+    /// coroutine, for example given back by a runtime error. The
+    /// coroutine can then be resumed without argument, which emits the
+    /// same request again.
+    pub fn restore(&mut self, io: Io) {
+        let Io::Select(Err(request)) = io else {
+            return;
+        };
+
+        let slots = [
+            (Side::Left, true, request.left),
+            (Side::Left, false, request.left_write),
+            (Side::Right, true, request.right),
+            (Side::Right, false, request.right_write),
+        ];
+
+        for (side, read, io) in slots {
+            if let (Some(io), Some(i)) = (io, self.owner(side, read)) {
+                self.copies[i].restore(*io);
+            }
+        }
+    }
+
+    /// Returns the index of the copy reading from, or writing to, the
+    /// given stream.
+    fn owner(&self, side: Side, read: bool) -> Option<usize> {
+        self.copies
+            .iter()
+            .position(|copy| copy.target() == Some(side) && (copy.from() == side) == read)
+    }
+}
+
+impl Default for CopyBidirectional {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for CopyBidirectional {
+    type Output = (u64, u64);
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        CopyBidirectional::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtimes::mock::{run_select, MockStream};
+
+    use super::CopyBidirectional;
+
+    #[test]
+    fn copy_bidirectional() {
+        let mut left = MockStream::new()
+            .read("ping")
+            .eof()
+            .write("pong")
+            .shutdown();
+        let mut right = MockStream::new()
+            .write("ping")
+            .shutdown()
+            .read("pong")
+            .eof();
+
+        let output = run_select(&mut left, &mut right, CopyBidirectional::new());
+        assert_eq!(output, Ok((4, 4)));
+        assert!(left.is_done());
+        assert!(right.is_done());
+    }
+
+    #[test]
+    fn read_while_writing() {
+        // both peers send bytes before reading the ones of the other
+        let mut left = MockStream::new()
+            .read("hello")
+            .write("world")
+            .eof()
+            .shutdown();
+        let mut right = MockStream::new()
+            .read("world")
+            .write("hello")
+            .eof()
+            .shutdown();
+
+        let output = run_select(&mut left, &mut right, CopyBidirectional::new());
+        assert_eq!(output, Ok((5, 5)));
+        assert!(left.is_done());
+        assert!(right.is_done());
+    }
+}
@@ -0,0 +1,50 @@
+//! Module dedicated to the [`Flush`] I/O-free coroutine.
+
+use alloc::boxed::Box;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+/// I/O-free coroutine for flushing a stream.
+///
+/// Flushing makes sure that all the bytes buffered by the stream
+/// (for example by a TLS layer) are sent.
+#[derive(Debug, Default)]
+pub struct Flush;
+
+impl Flush {
+    /// Creates a new coroutine to flush a stream.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Makes the flush progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<(), Io> {
+        let Some(arg) = arg else {
+            debug!("break: need I/O to flush stream");
+            return Err(Io::Flush(Err(())));
+        };
+
+        debug!("resume after flushing stream");
+
+        let Io::Flush(Ok(())) = arg else {
+            let got = Box::new(arg);
+            return Err(Error::UnexpectedIo {
+                expected: "flush",
+                got,
+            }
+            .into());
+        };
+
+        Ok(())
+    }
+}
+
+impl Coroutine for Flush {
+    type Output = ();
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Flush::resume(self, arg)
+    }
+}
@@ -0,0 +1,135 @@
+//! Module dedicated to the [`Join`] coroutine combinator.
+
+use alloc::boxed::Box;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io, SelectOutput, Side};
+
+use super::select::{request, resume};
+
+/// Coroutine combinator interleaving two coroutines, each one
+/// targeting its own stream.
+///
+/// Requests of both coroutines are emitted together as an
+/// [`Io::Select`], so that the runtime can process the one targeting
+/// the first ready stream. The combinator terminates once both
+/// coroutines terminated.
+///
+/// See [`Select`] to wait for the first coroutine only.
+///
+/// [`Select`]: super::Select
+#[derive(Debug)]
+pub struct Join<L: Coroutine, R: Coroutine> {
+    left: L,
+    right: R,
+    left_io: Option<Box<Io>>,
+    right_io: Option<Box<Io>>,
+    left_output: Option<L::Output>,
+    right_output: Option<R::Output>,
+}
+
+impl<L: Coroutine, R: Coroutine> Join<L, R> {
+    /// Creates a new combinator interleaving the given coroutines.
+    pub fn new(left: L, right: R) -> Self {
+        Self {
+            left,
+            right,
+            left_io: None,
+            right_io: None,
+            left_output: None,
+            right_output: None,
+        }
+    }
+
+    /// Makes both coroutines progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<(L::Output, R::Output), Io> {
+        match arg {
+            None => {
+                if self.left_io.is_none() && self.left_output.is_none() {
+                    self.left_output = resume(&mut self.left, &mut self.left_io, None)?;
+                }
+
+                if self.right_io.is_none() && self.right_output.is_none() {
+                    self.right_output = resume(&mut self.right, &mut self.right_io, None)?;
+                }
+            }
+            Some(Io::Select(Ok(SelectOutput { side, io, pending }))) => match side {
+                Side::Left => {
+                    self.right_io = pending.right.or(pending.right_write);
+                    self.left_output = resume(&mut self.left, &mut self.left_io, Some(*io))?;
+                }
+                Side::Right => {
+                    self.left_io = pending.left.or(pending.left_write);
+                    self.right_output = resume(&mut self.right, &mut self.right_io, Some(*io))?;
+                }
+            },
+            Some(arg) => {
+                let got = Box::new(arg);
+                return Err(Error::UnexpectedIo {
+                    expected: "select",
+                    got,
+                }
+                .into());
+            }
+        }
+
+        if self.left_output.is_some() && self.right_output.is_some() {
+            debug!("both coroutines terminated");
+            let left = self.left_output.take().unwrap();
+            let right = self.right_output.take().unwrap();
+            return Ok((left, right));
+        }
+
+        Err(request(self.left_io.take(), self.right_io.take()))
+    }
+}
+
+impl<L: Coroutine, R: Coroutine> Coroutine for Join<L, R> {
+    type Output = (L::Output, R::Output);
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Join::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{ReadExact, WriteAll},
+        runtimes::mock::{run_select, MockStream},
+        Io, SelectRequest,
+    };
+
+    use super::Join;
+
+    #[test]
+    fn route_writes() {
+        let mut join = Join::new(ReadExact::new(4), WriteAll::new(*b"request"));
+
+        let Err(Io::Select(Err(request))) = join.resume(None) else {
+            panic!("expected select request");
+        };
+
+        let expected = SelectRequest {
+            left: Some(Box::new(Io::Read(Err(vec![0; 4])))),
+            right_write: Some(Box::new(Io::Write(Err(b"request".to_vec())))),
+            ..SelectRequest::default()
+        };
+
+        assert_eq!(request, expected);
+    }
+
+    #[test]
+    fn both_terminated() {
+        let mut left = MockStream::new().read("ab").pending().read("cd");
+        let mut right = MockStream::new().write("req").pending().write("uest");
+
+        let join = Join::new(ReadExact::new(4), WriteAll::new(*b"request"));
+        let output = run_select(&mut left, &mut right, join).unwrap();
+
+        assert_eq!(output, (b"abcd".to_vec(), 7));
+        assert!(left.is_done());
+        assert!(right.is_done());
+    }
+}
@@ -0,0 +1,158 @@
+//! Module dedicated to the [`Lines`] I/O-free coroutine.
+
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+
+use crate::{Coroutine, Io};
+
+use super::{BufferedReader, LineEnding, ReadLine};
+
+/// I/O-free coroutine for reading lines.
+///
+/// The coroutine returns one line at a time, and can be resumed again
+/// to read the next one. It returns `None` once the stream reached
+/// EOF.
+///
+/// See [`ReadLine`] for how lines are read.
+#[derive(Debug)]
+pub struct Lines<R = BufferedReader> {
+    read: ReadLine<R>,
+}
+
+impl Lines {
+    /// Creates a new coroutine to read lines, with a read buffer
+    /// capacity of 1024.
+    ///
+    /// See [`Lines::with_capacity`] for a different buffer capacity.
+    pub fn new() -> Self {
+        Self::with_reader(BufferedReader::new())
+    }
+
+    /// Creates a new coroutine to read lines, with the given read
+    /// buffer capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_reader(BufferedReader::with_capacity(capacity))
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> Lines<R> {
+    /// Creates a new coroutine to read lines, using the given
+    /// buffered reader.
+    pub fn with_reader(reader: R) -> Self {
+        Self {
+            read: ReadLine::with_reader(reader),
+        }
+    }
+
+    /// Changes the accepted line ending.
+    ///
+    /// See [`ReadLine::line_ending`].
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.read = self.read.line_ending(ending);
+        self
+    }
+
+    /// Changes the maximum length of a line.
+    ///
+    /// See [`ReadLine::max_len`].
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.read = self.read.max_len(max_len);
+        self
+    }
+
+    /// Returns the bytes read after the last line.
+    pub fn remaining(&self) -> &[u8] {
+        self.read.remaining()
+    }
+
+    /// Consumes the coroutine and returns the inner buffered reader.
+    pub fn into_reader(self) -> R {
+        self.read.into_reader()
+    }
+
+    /// Makes the read progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<Option<Vec<u8>>, Io> {
+        self.read.next_line(arg)
+    }
+}
+
+impl Default for Lines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> Coroutine for Lines<R> {
+    type Output = Option<Vec<u8>>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Lines::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::LineEnding,
+        runtimes::mock::{handle, MockStream},
+        Error::LineTooLong,
+        Io,
+    };
+
+    use super::Lines;
+
+    #[test]
+    fn lines() {
+        let mut stream = MockStream::new().read("* OK\r\n* BYE\r\n").eof();
+
+        let mut lines = Lines::with_capacity(4).line_ending(LineEnding::Crlf);
+        let mut arg = None;
+        let mut outputs = Vec::new();
+
+        loop {
+            match lines.resume(arg.take()) {
+                Ok(Some(line)) => outputs.push(line),
+                Ok(None) => break,
+                Err(io) => arg = Some(handle(&mut stream, io).unwrap()),
+            }
+        }
+
+        assert_eq!(outputs, [b"* OK".to_vec(), b"* BYE".to_vec()]);
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn recover_after_overlong_line() {
+        let mut stream = MockStream::new()
+            .read("ok\nthis line is")
+            .read(" too long\nok again\nnot buffered yet and too long\nbye\n")
+            .eof();
+
+        let mut lines = Lines::with_capacity(16).max_len(8);
+        let mut arg = None;
+        let mut outputs = Vec::new();
+
+        loop {
+            match lines.resume(arg.take()) {
+                Ok(Some(line)) => outputs.push(Ok(line)),
+                Ok(None) => break,
+                Err(Io::Error(err)) => outputs.push(Err(err)),
+                Err(io) => arg = Some(handle(&mut stream, io).unwrap()),
+            }
+        }
+
+        let err = LineTooLong { max_len: 8 };
+
+        assert_eq!(
+            outputs,
+            [
+                Ok(b"ok".to_vec()),
+                Err(err.clone()),
+                Ok(b"ok again".to_vec()),
+                Err(err),
+                Ok(b"bye".to_vec()),
+            ]
+        );
+        assert!(stream.is_done());
+    }
+}
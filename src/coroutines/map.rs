@@ -0,0 +1,57 @@
+//! Module dedicated to the [`Map`] coroutine combinator.
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+/// Coroutine combinator mapping the output of a coroutine.
+///
+/// See [`Coroutine::map`].
+#[derive(Debug)]
+pub struct Map<C, F> {
+    coroutine: C,
+    f: Option<F>,
+}
+
+impl<C, F> Map<C, F> {
+    /// Creates a new combinator mapping the output of the given
+    /// coroutine with the given function.
+    pub fn new(coroutine: C, f: F) -> Self {
+        Self {
+            coroutine,
+            f: Some(f),
+        }
+    }
+}
+
+impl<C: Coroutine, F: FnOnce(C::Output) -> T, T> Coroutine for Map<C, F> {
+    type Output = T;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        let output = self.coroutine.resume(arg)?;
+
+        let Some(f) = self.f.take() else {
+            debug!("map resumed after termination");
+            return Err(Error::BufferNotReady.into());
+        };
+
+        Ok(f(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::ReadExact,
+        runtimes::mock::{run, MockStream},
+        Coroutine,
+    };
+
+    #[test]
+    fn map() {
+        let mut stream = MockStream::new().read("42\r\n");
+        let read = ReadExact::new(2).map(|bytes| bytes.len());
+
+        assert_eq!(run(&mut stream, read), Ok(2));
+    }
+}
@@ -2,17 +2,63 @@
 //! machines.
 //!
 //! Coroutines emit [`Io`] requests that need to be processed by
-//! [runtimes] in order to continue their progression.
+//! [runtimes] in order to continue their progression. They all
+//! implement the [`Coroutine`] trait.
 //!
 //! [`Io`]: crate::Io
+//! [`Coroutine`]: crate::Coroutine
 //! [runtimes]: crate::runtimes
 
+#[path = "and-then.rs"]
+mod and_then;
+#[path = "buffered-reader.rs"]
+mod buffered_reader;
+#[path = "copy-bidirectional.rs"]
+mod copy_bidirectional;
+mod flush;
+mod join;
+mod lines;
+mod map;
+mod pipe;
 mod read;
 #[path = "read-exact.rs"]
 mod read_exact;
+#[path = "read-line.rs"]
+mod read_line;
 #[path = "read-to-end.rs"]
 mod read_to_end;
+#[path = "read-until.rs"]
+mod read_until;
+mod select;
+mod shutdown;
+mod then;
+mod timeout;
 mod write;
+#[path = "write-all.rs"]
+mod write_all;
+#[path = "write-vectored.rs"]
+mod write_vectored;
 
 #[doc(inline)]
-pub use self::{read::Read, read_exact::ReadExact, read_to_end::ReadToEnd, write::Write};
+pub use self::{
+    and_then::AndThen,
+    buffered_reader::BufferedReader,
+    copy_bidirectional::CopyBidirectional,
+    flush::Flush,
+    join::Join,
+    lines::Lines,
+    map::Map,
+    pipe::Pipe,
+    read::Read,
+    read_exact::ReadExact,
+    read_line::{LineEnding, ReadLine},
+    read_to_end::ReadToEnd,
+    read_until::ReadUntil,
+    select::{Either, Select, Suspended},
+    shutdown::Shutdown,
+    then::Then,
+    timeout::Timeout,
+    write::Write,
+    write_all::WriteAll,
+    write_vectored::WriteVectored,
+};
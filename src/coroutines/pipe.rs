@@ -0,0 +1,336 @@
+//! Module dedicated to the [`Pipe`] I/O-free coroutine.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io, SelectOutput, SelectRequest, Side};
+
+/// The state of a [`Pipe`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    Read,
+    Write,
+    Close,
+    Done,
+}
+
+/// I/O-free coroutine for copying bytes from a stream to another one,
+/// until EOF.
+///
+/// Bytes are read from the left stream, then written to the right
+/// stream, using requests tagged with [`Io::Select`]. A single buffer
+/// is used for the whole copy, and partial writes are honored. The
+/// right stream is flushed once the left stream reached EOF, then the
+/// coroutine terminates with the total amount of bytes copied.
+///
+/// See [`CopyBidirectional`] to copy bytes in both directions.
+///
+/// [`CopyBidirectional`]: super::CopyBidirectional
+#[derive(Debug)]
+pub struct Pipe {
+    from: Side,
+    capacity: usize,
+    buffer: Option<Vec<u8>>,
+    state: State,
+    shutdown: bool,
+    bytes_count: u64,
+}
+
+impl Pipe {
+    /// Creates a new coroutine to copy bytes from the left stream to
+    /// the right stream, with a buffer capacity of 1024.
+    pub fn new() -> Self {
+        Self::with_capacity(1024)
+    }
+
+    /// Creates a new coroutine to copy bytes from the left stream to
+    /// the right stream, with the given buffer capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            from: Side::Left,
+            capacity,
+            buffer: Some(vec![0; capacity]),
+            state: State::Read,
+            shutdown: false,
+            bytes_count: 0,
+        }
+    }
+
+    /// Creates a new coroutine to copy bytes from the given stream to
+    /// the other one, shutting down the latter once the former
+    /// reached EOF.
+    pub(super) fn one_way(from: Side, capacity: usize) -> Self {
+        Self {
+            from,
+            shutdown: true,
+            ..Self::with_capacity(capacity)
+        }
+    }
+
+    /// Makes the copy progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<u64, Io> {
+        if let Some(arg) = arg {
+            let Io::Select(Ok(SelectOutput { io, pending, .. })) = arg else {
+                let got = Box::new(arg);
+                return Err(Error::UnexpectedIo {
+                    expected: "select",
+                    got,
+                }
+                .into());
+            };
+
+            self.restore(Io::Select(Err(pending)));
+
+            if let Some(bytes_count) = self.respond(*io)? {
+                return Ok(bytes_count);
+            }
+        }
+
+        let (Some(side), Some(io)) = (self.target(), self.request()) else {
+            return Err(Error::BufferNotReady.into());
+        };
+
+        let mut request = SelectRequest::default();
+
+        let read = matches!(io, Io::Read(_));
+
+        match (side, read) {
+            (Side::Left, true) => request.left = Some(Box::new(io)),
+            (Side::Left, false) => request.left_write = Some(Box::new(io)),
+            (Side::Right, true) => request.right = Some(Box::new(io)),
+            (Side::Right, false) => request.right_write = Some(Box::new(io)),
+        }
+
+        Err(Io::Select(Err(request)))
+    }
+
+    /// Returns the total amount of bytes copied so far.
+    pub fn bytes_count(&self) -> u64 {
+        self.bytes_count
+    }
+
+    /// Returns the stream the bytes are copied from.
+    pub(super) fn from(&self) -> Side {
+        self.from
+    }
+
+    /// Returns the stream targeted by the next request, or `None` if
+    /// the copy terminated.
+    pub(super) fn target(&self) -> Option<Side> {
+        let to = match self.from {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        };
+
+        match self.state {
+            State::Read => Some(self.from),
+            State::Write | State::Close => Some(to),
+            State::Done => None,
+        }
+    }
+
+    /// Builds the next request, or returns `None` if the copy
+    /// terminated or if the buffer is owned by the runtime.
+    pub(super) fn request(&mut self) -> Option<Io> {
+        match self.state {
+            State::Read => {
+                let mut buffer = self.buffer.take()?;
+                buffer.resize(self.capacity, 0);
+                debug!("break: need I/O to read bytes to copy");
+                Some(Io::Read(Err(buffer)))
+            }
+            State::Write => {
+                let buffer = self.buffer.take()?;
+                debug!("break: need I/O to write {} copied bytes", buffer.len());
+                Some(Io::Write(Err(buffer)))
+            }
+            State::Close if self.shutdown => Some(Io::Shutdown(Err(()))),
+            State::Close => Some(Io::Flush(Err(()))),
+            State::Done => None,
+        }
+    }
+
+    /// Takes back the buffer of the given request, given back
+    /// untouched by the runtime.
+    ///
+    /// The request can be the select request emitted by the pipe, for
+    /// example given back by a runtime error, or one of its inner
+    /// requests. The pipe can then be resumed without argument, which
+    /// emits the same request again.
+    pub fn restore(&mut self, io: Io) {
+        match io {
+            Io::Select(Err(request)) => {
+                let SelectRequest {
+                    left,
+                    right,
+                    left_write,
+                    right_write,
+                } = request;
+
+                for io in [left, right, left_write, right_write].into_iter().flatten() {
+                    self.restore(*io);
+                }
+            }
+            Io::Read(Err(buffer)) | Io::Write(Err(buffer)) => {
+                self.buffer = Some(buffer);
+            }
+            _ => (),
+        }
+    }
+
+    /// Processes the response of the last request, and returns the
+    /// total amount of bytes copied once the copy terminated.
+    pub(super) fn respond(&mut self, io: Io) -> Result<Option<u64>, Io> {
+        match (self.state, io) {
+            (State::Read, Io::Read(Ok(mut output))) => {
+                if output.bytes_count == 0 {
+                    debug!("reached EOF after copying {} bytes", self.bytes_count);
+                    self.state = State::Close;
+                } else {
+                    debug!("read {} bytes to copy", output.bytes_count);
+                    output.buffer.truncate(output.bytes_count);
+                    self.state = State::Write;
+                }
+
+                self.buffer = Some(output.buffer);
+                Ok(None)
+            }
+            (State::Write, Io::Write(Ok(mut output))) => {
+                if output.bytes_count == 0 {
+                    let remaining = output.buffer.len();
+                    self.buffer = Some(output.buffer);
+                    return Err(Error::WriteZero { remaining }.into());
+                }
+
+                if output.bytes_count > output.buffer.len() {
+                    debug!("wrote more bytes than submitted");
+                    let got = Box::new(Io::Write(Ok(output)));
+                    return Err(Error::UnexpectedIo {
+                        expected: "write",
+                        got,
+                    }
+                    .into());
+                }
+
+                debug!("wrote {} copied bytes", output.bytes_count);
+                output.buffer.drain(..output.bytes_count);
+                self.bytes_count += output.bytes_count as u64;
+
+                if output.buffer.is_empty() {
+                    self.state = State::Read;
+                }
+
+                self.buffer = Some(output.buffer);
+                Ok(None)
+            }
+            (State::Close, Io::Flush(Ok(()))) | (State::Close, Io::Shutdown(Ok(()))) => {
+                self.state = State::Done;
+                Ok(Some(self.bytes_count))
+            }
+            (State::Done, _) => Err(Error::BufferNotReady.into()),
+            (state, io) => {
+                let expected = match state {
+                    State::Read => "read",
+                    State::Write => "write",
+                    State::Close if self.shutdown => "shutdown",
+                    State::Close | State::Done => "flush",
+                };
+
+                let got = Box::new(io);
+                Err(Error::UnexpectedIo { expected, got }.into())
+            }
+        }
+    }
+}
+
+impl Default for Pipe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coroutine for Pipe {
+    type Output = u64;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Pipe::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtimes::mock::{run_select, MockStream},
+        Error::{BufferNotReady, UnexpectedIo, WriteZero},
+        Io, Output, SelectOutput, SelectRequest, Side,
+    };
+
+    use super::Pipe;
+
+    #[test]
+    fn pipe() {
+        let mut left = MockStream::new().read("hello ").read("world").eof();
+        let mut right = MockStream::new()
+            .write("hel")
+            .write("lo ")
+            .write("world")
+            .flush();
+
+        let output = run_select(&mut left, &mut right, Pipe::with_capacity(8));
+        assert_eq!(output, Ok(11));
+        assert!(left.is_done());
+        assert!(right.is_done());
+    }
+
+    #[test]
+    fn write_zero() {
+        let mut left = MockStream::new().read("hello");
+        let mut right = MockStream::new().write("");
+
+        let err = run_select(&mut left, &mut right, Pipe::new()).unwrap_err();
+        let expected = WriteZero { remaining: 5 };
+        assert_eq!(err, crate::runtimes::mock::Error::Coroutine(expected));
+    }
+
+    #[test]
+    fn write_too_many() {
+        let mut pipe = Pipe::with_capacity(4);
+        pipe.resume(None).unwrap_err();
+
+        let respond = |side, io| {
+            let io = Box::new(io);
+            let pending = SelectRequest::default();
+            Some(Io::Select(Ok(SelectOutput { side, io, pending })))
+        };
+
+        let read = Io::Read(Ok(Output {
+            buffer: b"abcd".to_vec(),
+            bytes_count: 4,
+        }));
+        pipe.resume(respond(Side::Left, read)).unwrap_err();
+
+        let write = Io::Write(Ok(Output {
+            buffer: b"abcd".to_vec(),
+            bytes_count: 5,
+        }));
+        let err = pipe.resume(respond(Side::Right, write.clone()));
+
+        let expected = UnexpectedIo {
+            expected: "write",
+            got: Box::new(write),
+        };
+        assert_eq!(err, Err(Io::Error(expected)));
+    }
+
+    #[test]
+    fn restore() {
+        let mut pipe = Pipe::with_capacity(4);
+
+        let io = pipe.resume(None).unwrap_err();
+        assert_eq!(pipe.resume(None), Err(Io::Error(BufferNotReady)));
+
+        pipe.restore(io.clone());
+        assert_eq!(pipe.resume(None), Err(io));
+    }
+}
@@ -1,134 +1,190 @@
-//! Module dedicated to the [`Read`] I/O-free coroutine.
+//! Module dedicated to the [`ReadExact`] I/O-free coroutine.
+
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
 
 use log::debug;
 
-use crate::Io;
+use crate::{Coroutine, Error, Io};
 
-use super::Read;
+use super::BufferedReader;
 
-/// I/O-free coroutine for reading bytes into a buffer until it
-/// reaches EOF.
+/// I/O-free coroutine for reading an exact amount of bytes.
+///
+/// The coroutine never reads more bytes than needed from the stream,
+/// whatever the size of the reads returned by the runtime.
 #[derive(Debug)]
-pub struct ReadExact {
-    read: Read,
+pub struct ReadExact<R = BufferedReader> {
+    reader: R,
     count: usize,
-    buffer: Option<Vec<u8>>,
 }
 
 impl ReadExact {
+    /// Creates a new coroutine to read the given amount of bytes,
+    /// with a read buffer capacity of 1024.
+    ///
+    /// See [`ReadExact::with_capacity`] for a different buffer
+    /// capacity.
     pub fn new(bytes_count: usize) -> Self {
-        Self {
-            read: Read::new(),
-            count: bytes_count,
-            buffer: Some(Vec::new()),
-        }
+        Self::with_reader(BufferedReader::new(), bytes_count)
     }
 
-    /// Creates a new read coroutine with the given buffer capacity.
+    /// Creates a new coroutine to read the given amount of bytes,
+    /// with the given read buffer capacity.
     pub fn with_capacity(capacity: usize, bytes_count: usize) -> Self {
+        Self::with_reader(BufferedReader::with_capacity(capacity), bytes_count)
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> ReadExact<R> {
+    /// Creates a new coroutine to read the given amount of bytes,
+    /// consuming first the bytes of the given buffered reader.
+    pub fn with_reader(reader: R, bytes_count: usize) -> Self {
         Self {
-            read: Read::with_capacity(capacity),
+            reader,
             count: bytes_count,
-            buffer: Some(Vec::new()),
         }
     }
 
+    /// Consumes the coroutine and returns the inner buffered reader.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
     /// Makes the read progress.
     pub fn resume(&mut self, mut arg: Option<Io>) -> Result<Vec<u8>, Io> {
-        loop {
-            let Some(buffer) = &mut self.buffer else {
-                return Err(Io::err("read exact buffer not ready"));
-            };
+        let reader = self.reader.borrow_mut();
 
-            if self.count == 0 {
-                break Ok(self.buffer.take().unwrap());
-            }
+        loop {
+            let got = reader.buffer().len();
 
-            if self.count < self.read.capacity() {
-                self.read = Read::with_capacity(self.count);
+            if arg.is_none() && got >= self.count {
+                debug!("read {} bytes", self.count);
+                break Ok(reader.consume(self.count));
             }
 
-            let output = self.read.resume(arg.take())?;
+            let remaining = self.count - got;
 
-            if output.bytes_count == 0 {
-                debug!("expected {} more bytes, got unexpected EOF", self.count);
-                break Err(Io::err("read 0 bytes, unexpected EOF?"));
+            if reader.fill_at_most(remaining, arg.take())? == 0 {
+                debug!("expected {remaining} more bytes, got unexpected EOF");
+                let expected = self.count;
+                break Err(Error::UnexpectedEof { expected, got }.into());
             }
-
-            buffer.extend(output.bytes());
-            self.count -= self.read.capacity();
-            self.read.replace(output.buffer);
         }
     }
 }
 
+impl<R: BorrowMut<BufferedReader>> Coroutine for ReadExact<R> {
+    type Output = Vec<u8>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        ReadExact::resume(self, arg)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
+    use proptest::prelude::*;
 
-    use crate::{Io, Output};
+    use crate::{
+        runtimes::mock::{run, Error, MockStream, Step},
+        Error::UnexpectedEof,
+    };
 
     use super::ReadExact;
 
     #[test]
     fn read_exact_smaller_capacity() {
-        let mut reader = BufReader::new("abcdef".as_bytes());
-
-        let mut read = ReadExact::with_capacity(3, 4);
-        let mut arg = None;
-
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
-
-        assert_eq!(output, b"abcd");
-
-        let mut remaining = vec![0; 4];
-        let bytes_count = reader.read(&mut remaining).unwrap();
-
-        assert_eq!(bytes_count, 2);
-        assert_eq!(&remaining[..bytes_count], b"ef");
+        let mut stream = MockStream::new().read("abcdef");
+        let output = run(&mut stream, ReadExact::with_capacity(3, 4));
+
+        assert_eq!(output, Ok(b"abcd".to_vec()));
+        assert_eq!(
+            stream.steps().collect::<Vec<_>>(),
+            [&Step::Read(b"ef".to_vec())]
+        );
     }
 
     #[test]
     fn read_exact_bigger_capacity() {
-        let mut reader = BufReader::new("abcdef".as_bytes());
-
-        let mut read = ReadExact::with_capacity(5, 4);
-        let mut arg = None;
-
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
+        let mut stream = MockStream::new().read("abcdef");
+        let output = run(&mut stream, ReadExact::with_capacity(5, 4));
+
+        assert_eq!(output, Ok(b"abcd".to_vec()));
+        assert_eq!(
+            stream.steps().collect::<Vec<_>>(),
+            [&Step::Read(b"ef".to_vec())]
+        );
+    }
+
+    /// Runs the given coroutine against the given bytes, with reads
+    /// returning chunks of the given sizes (cycled).
+    ///
+    /// Returns the output and the amount of bytes read from the
+    /// stream.
+    fn run_chunked(
+        read: ReadExact,
+        bytes: &[u8],
+        chunks: &[usize],
+    ) -> (Result<Vec<u8>, Error>, usize) {
+        let mut stream = MockStream::new();
+        let mut chunks = chunks.iter().cycle();
+        let mut position = 0;
+
+        while position < bytes.len() {
+            let end = bytes.len().min(position + chunks.next().unwrap());
+            stream = stream.read(&bytes[position..end]);
+            position = end;
+        }
+
+        let mut stream = stream.eof();
+        let output = run(&mut stream, read);
+
+        let unread: usize = stream
+            .steps()
+            .map(|step| match step {
+                Step::Read(bytes) => bytes.len(),
+                _ => 0,
+            })
+            .sum();
+
+        (output, bytes.len() - unread)
+    }
 
-        assert_eq!(output, b"abcd");
+    proptest! {
+        #[test]
+        fn read_exact_short_reads(
+            bytes in prop::collection::vec(any::<u8>(), 0..512),
+            count in 0..512usize,
+            capacity in 1..64usize,
+            chunks in prop::collection::vec(1..64usize, 1..16),
+        ) {
+            let count = count.min(bytes.len());
+            let read = ReadExact::with_capacity(capacity, count);
+            let (output, position) = run_chunked(read, &bytes, &chunks);
+
+            prop_assert_eq!(output, Ok(bytes[..count].to_vec()));
+            prop_assert_eq!(position, count);
+        }
 
-        let mut remaining = vec![0; 4];
-        let bytes_count = reader.read(&mut remaining).unwrap();
+        #[test]
+        fn read_exact_unexpected_eof(
+            bytes in prop::collection::vec(any::<u8>(), 0..512),
+            missing in 1..64usize,
+            capacity in 1..64usize,
+            chunks in prop::collection::vec(1..64usize, 1..16),
+        ) {
+            let count = bytes.len() + missing;
+            let read = ReadExact::with_capacity(capacity, count);
+            let (output, position) = run_chunked(read, &bytes, &chunks);
+
+            let err = UnexpectedEof {
+                expected: count,
+                got: bytes.len(),
+            };
 
-        assert_eq!(bytes_count, 2);
-        assert_eq!(&remaining[..bytes_count], b"ef");
+            prop_assert_eq!(output, Err(Error::Coroutine(err)));
+            prop_assert_eq!(position, bytes.len());
+        }
     }
 }
@@ -0,0 +1,304 @@
+//! Module dedicated to the [`ReadLine`] I/O-free coroutine.
+
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+use super::BufferedReader;
+
+/// The line termination accepted by line-oriented coroutines.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LineEnding {
+    /// Lines are terminated by `\r\n` only, as required by most text
+    /// protocols (SMTP, IMAP, POP3…).
+    ///
+    /// A bare `\n` is considered part of the line.
+    Crlf,
+
+    /// Lines are terminated by `\n`, optionally preceded by `\r`.
+    ///
+    /// The last line is returned even if it is not terminated.
+    #[default]
+    Lf,
+}
+
+impl LineEnding {
+    fn len(&self) -> usize {
+        match self {
+            Self::Crlf => 2,
+            Self::Lf => 1,
+        }
+    }
+}
+
+/// I/O-free coroutine for reading one line.
+///
+/// The returned line does not contain the line ending. Bytes read
+/// after the line are kept in the inner [`BufferedReader`].
+///
+/// A line longer than the maximum length emits
+/// [`Error::LineTooLong`], and is discarded: resuming the coroutine
+/// again skips the rest of the line, then reads the next one.
+#[derive(Debug)]
+pub struct ReadLine<R = BufferedReader> {
+    reader: R,
+    ending: LineEnding,
+    max_len: usize,
+    searched: usize,
+    discarding: bool,
+}
+
+impl ReadLine {
+    /// The default maximum length of a line, line ending excluded.
+    pub const DEFAULT_MAX_LEN: usize = 8192;
+
+    /// Creates a new coroutine to read one line, with a read buffer
+    /// capacity of 1024.
+    ///
+    /// See [`ReadLine::with_capacity`] for a different buffer
+    /// capacity.
+    pub fn new() -> Self {
+        Self::with_reader(BufferedReader::new())
+    }
+
+    /// Creates a new coroutine to read one line, with the given read
+    /// buffer capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_reader(BufferedReader::with_capacity(capacity))
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> ReadLine<R> {
+    /// Creates a new coroutine to read one line, using the given
+    /// buffered reader.
+    pub fn with_reader(reader: R) -> Self {
+        Self {
+            reader,
+            ending: LineEnding::default(),
+            max_len: ReadLine::DEFAULT_MAX_LEN,
+            searched: 0,
+            discarding: false,
+        }
+    }
+
+    /// Changes the accepted line ending (defaults to
+    /// [`LineEnding::Lf`]).
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.ending = ending;
+        self
+    }
+
+    /// Changes the maximum length of a line, line ending excluded
+    /// (defaults to [`ReadLine::DEFAULT_MAX_LEN`]).
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Returns the bytes read after the last line.
+    pub fn remaining(&self) -> &[u8] {
+        self.reader.borrow().buffer()
+    }
+
+    /// Consumes the coroutine and returns the inner buffered reader.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
+    /// Makes the read progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<Vec<u8>, Io> {
+        match self.next_line(arg)? {
+            Some(line) => Ok(line),
+            None => {
+                let expected = self.ending.len();
+                Err(Error::UnexpectedEof { expected, got: 0 }.into())
+            }
+        }
+    }
+
+    /// Makes the read progress, returning `None` if the stream
+    /// reached EOF before any byte of a new line.
+    pub(crate) fn next_line(&mut self, mut arg: Option<Io>) -> Result<Option<Vec<u8>>, Io> {
+        loop {
+            if arg.is_none() {
+                if let Some(line) = self.take_line()? {
+                    break Ok(Some(line));
+                }
+            }
+
+            if self.reader.borrow_mut().fill(arg.take())? > 0 {
+                continue;
+            }
+
+            let reader = self.reader.borrow_mut();
+
+            if self.discarding {
+                debug!("reached EOF, discard the end of the overlong line");
+                reader.consume(usize::MAX);
+                self.searched = 0;
+                self.discarding = false;
+            }
+
+            if reader.is_empty() {
+                debug!("reached EOF");
+                break Ok(None);
+            }
+
+            if self.ending == LineEnding::Lf {
+                let line = reader.consume(usize::MAX);
+                self.searched = 0;
+                debug!("reached EOF, return unterminated line");
+                break Ok(Some(line));
+            }
+
+            debug!("expected CRLF, got unexpected EOF");
+            let got = reader.buffer().len();
+            let expected = got + self.ending.len();
+            break Err(Error::UnexpectedEof { expected, got }.into());
+        }
+    }
+
+    fn take_line(&mut self) -> Result<Option<Vec<u8>>, Io> {
+        let reader = self.reader.borrow_mut();
+
+        let (mut n, mut line) = loop {
+            let buffer = reader.buffer();
+
+            let found = match self.ending {
+                LineEnding::Crlf => buffer[self.searched..]
+                    .windows(2)
+                    .position(|window| window == b"\r\n")
+                    .map(|n| (self.searched + n, 2)),
+                LineEnding::Lf => buffer[self.searched..]
+                    .iter()
+                    .position(|byte| *byte == b'\n')
+                    .map(|n| (self.searched + n, 1)),
+            };
+
+            let Some((n, len)) = found else {
+                // a line ending may start with the last byte of the
+                // buffer, so it needs to be searched again
+                self.searched = buffer.len().saturating_sub(1);
+
+                if self.discarding {
+                    reader.consume(self.searched);
+                    self.searched = 0;
+                    return Ok(None);
+                }
+
+                if self.searched > self.max_len {
+                    let max_len = self.max_len;
+                    debug!("line exceeds {max_len} bytes, discard it");
+                    reader.consume(self.searched);
+                    self.searched = 0;
+                    self.discarding = true;
+                    return Err(Error::LineTooLong { max_len }.into());
+                }
+
+                return Ok(None);
+            };
+
+            self.searched = 0;
+            let line = reader.consume(n + len);
+
+            if !self.discarding {
+                break (n, line);
+            }
+
+            debug!("discarded the end of the overlong line");
+            self.discarding = false;
+        };
+
+        if self.ending == LineEnding::Lf && n > 0 && line[n - 1] == b'\r' {
+            n -= 1;
+        }
+
+        line.truncate(n);
+
+        if n > self.max_len {
+            let max_len = self.max_len;
+            debug!("line of {n} bytes exceeds {max_len} bytes");
+            return Err(Error::LineTooLong { max_len }.into());
+        }
+
+        Ok(Some(line))
+    }
+}
+
+impl Default for ReadLine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> Coroutine for ReadLine<R> {
+    type Output = Vec<u8>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        ReadLine::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtimes::mock::{run, Error, MockStream},
+        Error::{LineTooLong, UnexpectedEof},
+    };
+
+    use super::{LineEnding, ReadLine};
+
+    #[test]
+    fn read_line_lf() {
+        let mut stream = MockStream::new().read("ab\r\ncd\nef").eof().eof();
+        let mut read = ReadLine::with_capacity(3);
+
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"ab");
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"cd");
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"ef");
+
+        let err = UnexpectedEof {
+            expected: 1,
+            got: 0,
+        };
+
+        assert_eq!(run(&mut stream, &mut read), Err(Error::Coroutine(err)));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn read_line_crlf() {
+        let mut stream = MockStream::new().read("a\nb\r\ncd").eof();
+        let mut read = ReadLine::with_capacity(2).line_ending(LineEnding::Crlf);
+
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"a\nb");
+
+        let err = UnexpectedEof {
+            expected: 4,
+            got: 2,
+        };
+
+        assert_eq!(run(&mut stream, &mut read), Err(Error::Coroutine(err)));
+        assert_eq!(read.remaining(), b"cd");
+    }
+
+    #[test]
+    fn read_line_too_long() {
+        let mut stream = MockStream::new().read("abc\r\nabcdef\r\nxy\r\n");
+        let mut read = ReadLine::with_capacity(2)
+            .line_ending(LineEnding::Crlf)
+            .max_len(3);
+
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"abc");
+
+        let err = LineTooLong { max_len: 3 };
+        assert_eq!(run(&mut stream, &mut read), Err(Error::Coroutine(err)));
+
+        // the rest of the overlong line is skipped
+        assert_eq!(run(&mut stream, &mut read).unwrap(), b"xy");
+        assert!(stream.is_done());
+    }
+}
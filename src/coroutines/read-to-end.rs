@@ -1,52 +1,92 @@
 //! Module dedicated to the [`Read`] I/O-free coroutine.
 
-use crate::Io;
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
 
-use super::read::Read;
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+use super::BufferedReader;
 
 /// I/O-free coroutine for reading bytes into a buffer until it
 /// reaches EOF.
+///
+/// The buffer grows without bound by default. See
+/// [`ReadToEnd::with_limit`] to read from untrusted streams.
 #[derive(Debug)]
-pub struct ReadToEnd {
-    read: Read,
-    buffer: Option<Vec<u8>>,
+pub struct ReadToEnd<R = BufferedReader> {
+    reader: R,
+    limit: usize,
 }
 
 impl ReadToEnd {
     pub fn new() -> Self {
-        Self {
-            read: Read::new(),
-            buffer: Some(Vec::new()),
-        }
+        Self::with_reader(BufferedReader::new())
     }
 
     /// Creates a new read coroutine with the given buffer capacity.
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_reader(BufferedReader::with_capacity(capacity))
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> ReadToEnd<R> {
+    /// Creates a new read coroutine that first consumes all bytes
+    /// from the given buffered reader.
+    pub fn with_reader(reader: R) -> Self {
         Self {
-            read: Read::with_capacity(capacity),
-            buffer: Some(Vec::new()),
+            reader,
+            limit: usize::MAX,
         }
     }
 
+    /// Changes the maximum amount of bytes to read.
+    ///
+    /// The coroutine emits [`Error::LimitExceeded`] as soon as the
+    /// stream sent more bytes than the limit, without reading the
+    /// remaining ones: read buffers are capped so that at most one
+    /// byte past the limit is ever requested.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Consumes the coroutine and returns the inner buffered reader.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
     /// Makes the read progress.
     pub fn resume(&mut self, mut arg: Option<Io>) -> Result<Vec<u8>, Io> {
-        loop {
-            let output = self.read.resume(arg.take())?;
+        let reader = self.reader.borrow_mut();
 
-            let Some(buffer) = &mut self.buffer else {
-                break Err(Io::err("read to end buffer not ready"));
-            };
+        loop {
+            let len = reader.buffer().len();
 
-            if output.bytes_count == 0 {
-                break Ok(self.buffer.take().unwrap());
+            if len > self.limit {
+                let limit = self.limit;
+                debug!("stream exceeds {limit} bytes");
+                break Err(Error::LimitExceeded { limit }.into());
             }
 
-            buffer.extend(output.bytes());
-            self.read.replace(output.buffer);
+            let max = self.limit.saturating_sub(len).saturating_add(1);
+
+            if reader.fill_at_most(max, arg.take())? == 0 {
+                break Ok(reader.consume(usize::MAX));
+            }
         }
     }
 }
 
+impl<R: BorrowMut<BufferedReader>> Coroutine for ReadToEnd<R> {
+    type Output = Vec<u8>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        ReadToEnd::resume(self, arg)
+    }
+}
+
 impl Default for ReadToEnd {
     fn default() -> Self {
         Self::new()
@@ -55,34 +95,39 @@ impl Default for ReadToEnd {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
-
-    use crate::{Io, Output};
+    use crate::{
+        runtimes::mock::{run, Error, MockStream, Step},
+        Error::LimitExceeded,
+    };
 
     use super::ReadToEnd;
 
     #[test]
     fn read_to_end() {
-        let mut reader = BufReader::new("abcdef".as_bytes());
-
-        let mut read = ReadToEnd::with_capacity(4);
-        let mut arg = None;
-
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
+        let mut stream = MockStream::new().read("abc").read("def").eof();
+        let output = run(&mut stream, ReadToEnd::with_capacity(4)).unwrap();
 
         assert_eq!(output, b"abcdef");
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn limit() {
+        let mut stream = MockStream::new().read("abc").read("def").eof();
+        let output = run(&mut stream, ReadToEnd::with_capacity(4).with_limit(6));
+        assert_eq!(output, Ok(b"abcdef".to_vec()));
+
+        let mut stream = MockStream::new().read("abc").read("def").read("ghi");
+        let err = run(&mut stream, ReadToEnd::with_capacity(4).with_limit(5)).unwrap_err();
+        assert_eq!(err, Error::Coroutine(LimitExceeded { limit: 5 }));
+        assert_eq!(stream.steps().count(), 1);
+
+        let mut stream = MockStream::new().read("abcdef");
+        let err = run(&mut stream, ReadToEnd::with_capacity(4).with_limit(2)).unwrap_err();
+        assert_eq!(err, Error::Coroutine(LimitExceeded { limit: 2 }));
+        assert_eq!(
+            stream.steps().collect::<Vec<_>>(),
+            [&Step::Read(b"def".to_vec())]
+        );
     }
 }
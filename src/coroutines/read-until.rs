@@ -0,0 +1,187 @@
+//! Module dedicated to the [`ReadUntil`] I/O-free coroutine.
+
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+use super::BufferedReader;
+
+/// I/O-free coroutine for reading bytes until a delimiter is found.
+///
+/// Bytes read after the delimiter are kept in the inner
+/// [`BufferedReader`], and are used first when the coroutine is
+/// resumed again.
+#[derive(Debug)]
+pub struct ReadUntil<R = BufferedReader> {
+    reader: R,
+    delimiter: Vec<u8>,
+    include_delimiter: bool,
+    searched: usize,
+}
+
+impl ReadUntil {
+    /// Creates a new coroutine to read bytes until the given
+    /// delimiter, with a read buffer capacity of 1024.
+    ///
+    /// See [`ReadUntil::with_capacity`] for a different buffer
+    /// capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the delimiter is empty.
+    pub fn new(delimiter: impl IntoIterator<Item = u8>) -> Self {
+        Self::with_reader(BufferedReader::new(), delimiter)
+    }
+
+    /// Creates a new coroutine to read bytes until the given
+    /// delimiter, with the given read buffer capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the delimiter is empty.
+    pub fn with_capacity(capacity: usize, delimiter: impl IntoIterator<Item = u8>) -> Self {
+        Self::with_reader(BufferedReader::with_capacity(capacity), delimiter)
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> ReadUntil<R> {
+    /// Creates a new coroutine to read bytes until the given
+    /// delimiter, using the given buffered reader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the delimiter is empty, since it would match before
+    /// any byte.
+    pub fn with_reader(reader: R, delimiter: impl IntoIterator<Item = u8>) -> Self {
+        let delimiter: Vec<u8> = delimiter.into_iter().collect();
+        assert!(!delimiter.is_empty(), "delimiter must not be empty");
+
+        Self {
+            reader,
+            delimiter,
+            include_delimiter: false,
+            searched: 0,
+        }
+    }
+
+    /// Includes the delimiter at the end of the returned bytes.
+    pub fn including_delimiter(mut self) -> Self {
+        self.include_delimiter = true;
+        self
+    }
+
+    /// Returns the bytes read after the last found delimiter.
+    pub fn remaining(&self) -> &[u8] {
+        self.reader.borrow().buffer()
+    }
+
+    /// Consumes the coroutine and returns the inner buffered reader.
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
+    /// Makes the read progress.
+    pub fn resume(&mut self, mut arg: Option<Io>) -> Result<Vec<u8>, Io> {
+        loop {
+            if arg.is_none() {
+                if let Some(bytes) = self.take_until_delimiter() {
+                    break Ok(bytes);
+                }
+            }
+
+            if self.reader.borrow_mut().fill(arg.take())? == 0 {
+                debug!("expected delimiter, got unexpected EOF");
+                let got = self.remaining().len();
+                let expected = got + self.delimiter.len();
+                break Err(Error::UnexpectedEof { expected, got }.into());
+            }
+        }
+    }
+
+    fn take_until_delimiter(&mut self) -> Option<Vec<u8>> {
+        let len = self.delimiter.len();
+        let buffer = self.reader.borrow().buffer();
+
+        let Some(n) = buffer[self.searched..]
+            .windows(len)
+            .position(|window| window.starts_with(&self.delimiter))
+            .map(|n| self.searched + n)
+        else {
+            // the delimiter may start in the last bytes of the
+            // buffer, so they need to be searched again
+            self.searched = (buffer.len() + 1).saturating_sub(len);
+            return None;
+        };
+
+        debug!("found delimiter at position {n}");
+
+        let mut bytes = self.reader.borrow_mut().consume(n + len);
+        self.searched = 0;
+
+        if !self.include_delimiter {
+            bytes.truncate(n);
+        }
+
+        Some(bytes)
+    }
+}
+
+impl<R: BorrowMut<BufferedReader>> Coroutine for ReadUntil<R> {
+    type Output = Vec<u8>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        ReadUntil::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtimes::mock::{run, Error, MockStream},
+        Error::UnexpectedEof,
+    };
+
+    use super::ReadUntil;
+
+    #[test]
+    fn read_until() {
+        let mut stream = MockStream::new().read("ab\r\ncd\r\n\r\nef");
+        let mut read = ReadUntil::with_capacity(3, *b"\r\n\r\n");
+
+        let output = run(&mut stream, &mut read).unwrap();
+
+        assert_eq!(output, b"ab\r\ncd");
+        assert_eq!(read.remaining(), b"ef");
+    }
+
+    #[test]
+    fn read_until_including_delimiter() {
+        let mut stream = MockStream::new().read("ab\ncd\nef").eof();
+        let mut read = ReadUntil::with_capacity(8, *b"\n").including_delimiter();
+
+        let output = run(&mut stream, &mut read).unwrap();
+        assert_eq!(output, b"ab\n");
+
+        // the second line is taken from the remaining bytes, without
+        // emitting any I/O
+        let output = read.resume(None).unwrap();
+        assert_eq!(output, b"cd\n");
+
+        let err = UnexpectedEof {
+            expected: 3,
+            got: 2,
+        };
+
+        assert_eq!(run(&mut stream, &mut read), Err(Error::Coroutine(err)));
+        assert_eq!(read.remaining(), b"ef");
+    }
+
+    #[test]
+    #[should_panic(expected = "delimiter must not be empty")]
+    fn empty_delimiter() {
+        ReadUntil::new([]);
+    }
+}
@@ -1,8 +1,10 @@
 //! Module dedicated to the [`Read`] I/O-free coroutine.
 
+use alloc::{boxed::Box, vec, vec::Vec};
+
 use log::debug;
 
-use crate::{Io, Output};
+use crate::{Coroutine, Error, Io, Output};
 
 /// I/O-free coroutine for reading bytes into a buffer.
 #[derive(Debug)]
@@ -44,7 +46,7 @@ impl Read {
     pub fn resume(&mut self, arg: Option<Io>) -> Result<Output, Io> {
         let Some(arg) = arg else {
             let Some(buffer) = self.buffer.take() else {
-                return Err(Io::err("read buffer not initialized"));
+                return Err(Error::BufferNotReady.into());
             };
 
             debug!("break: need I/O to read bytes");
@@ -54,8 +56,12 @@ impl Read {
         debug!("resume after reading bytes");
 
         let Io::Read(io) = arg else {
-            let err = format!("expected read output, got {arg:?}");
-            return Err(Io::err(err));
+            let got = Box::new(arg);
+            return Err(Error::UnexpectedIo {
+                expected: "read",
+                got,
+            }
+            .into());
         };
 
         let output = match io {
@@ -71,6 +77,14 @@ impl Read {
     }
 }
 
+impl Coroutine for Read {
+    type Output = Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Read::resume(self, arg)
+    }
+}
+
 impl Default for Read {
     fn default() -> Self {
         Self::new()
@@ -79,72 +93,27 @@ impl Default for Read {
 
 #[cfg(test)]
 mod tests {
-    use std::io::{BufReader, Read as _};
-
-    use crate::{Io, Output};
+    use crate::runtimes::mock::{run, MockStream};
 
     use super::Read;
 
     #[test]
     fn read() {
-        let mut reader = BufReader::new("abcdef".as_bytes());
-
+        let mut stream = MockStream::new().read("abcdef").eof();
         let mut read = Read::with_capacity(4);
-        let mut arg = None;
-
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
 
+        let output = run(&mut stream, &mut read).unwrap();
         assert_eq!(output.bytes(), b"abcd");
 
         read.replace(output.buffer);
 
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
-
+        let output = run(&mut stream, &mut read).unwrap();
         assert_eq!(output.bytes(), b"ef");
 
         read.replace(output.buffer);
 
-        let output = loop {
-            match read.resume(arg.take()) {
-                Ok(output) => break output,
-                Err(Io::Read(Err(mut buffer))) => {
-                    let bytes_count = reader.read(&mut buffer).unwrap();
-                    let output = Output {
-                        buffer,
-                        bytes_count,
-                    };
-                    arg = Some(Io::Read(Ok(output)))
-                }
-                Err(io) => unreachable!("unexpected I/O: {io:?}"),
-            }
-        };
-
+        let output = run(&mut stream, &mut read).unwrap();
         assert_eq!(output.bytes_count, 0);
+        assert!(stream.is_done());
     }
 }
@@ -0,0 +1,263 @@
+//! Module dedicated to the [`Select`] coroutine combinator.
+
+use alloc::boxed::Box;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io, SelectOutput, SelectRequest, Side};
+
+/// The output of a [`Select`], telling which coroutine terminated
+/// first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+/// Coroutine combinator racing two coroutines, each one targeting
+/// its own stream.
+///
+/// Requests of both coroutines are emitted together as an
+/// [`Io::Select`], so that the runtime can process the one targeting
+/// the first ready stream. The combinator terminates with the output
+/// of the first coroutine that terminates, together with the other
+/// one, suspended (see [`Suspended`]).
+///
+/// See [`Join`] to wait for both coroutines.
+///
+/// [`Join`]: super::Join
+#[derive(Debug)]
+pub struct Select<L, R> {
+    coroutines: Option<(L, R)>,
+    left_io: Option<Box<Io>>,
+    right_io: Option<Box<Io>>,
+}
+
+impl<L, R> Select<L, R> {
+    /// Creates a new combinator racing the given coroutines.
+    pub fn new(left: L, right: R) -> Self {
+        Self {
+            coroutines: Some((left, right)),
+            left_io: None,
+            right_io: None,
+        }
+    }
+}
+
+impl<L: Coroutine, R: Coroutine> Select<L, R> {
+    /// Makes the race progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<<Self as Coroutine>::Output, Io> {
+        let Some((left, right)) = &mut self.coroutines else {
+            debug!("select resumed after termination");
+            return Err(Error::BufferNotReady.into());
+        };
+
+        let output = match arg {
+            None => {
+                let mut output = None;
+
+                if self.left_io.is_none() {
+                    output = resume(left, &mut self.left_io, None)?.map(Either::Left);
+                }
+
+                if output.is_none() && self.right_io.is_none() {
+                    output = resume(right, &mut self.right_io, None)?.map(Either::Right);
+                }
+
+                output
+            }
+            Some(Io::Select(Ok(SelectOutput { side, io, pending }))) => match side {
+                Side::Left => {
+                    self.right_io = pending.right.or(pending.right_write);
+                    resume(left, &mut self.left_io, Some(*io))?.map(Either::Left)
+                }
+                Side::Right => {
+                    self.left_io = pending.left.or(pending.left_write);
+                    resume(right, &mut self.right_io, Some(*io))?.map(Either::Right)
+                }
+            },
+            Some(arg) => {
+                let got = Box::new(arg);
+                return Err(Error::UnexpectedIo {
+                    expected: "select",
+                    got,
+                }
+                .into());
+            }
+        };
+
+        match output {
+            Some(Either::Left(output)) => {
+                debug!("left coroutine terminated first");
+                let (_, right) = self.coroutines.take().unwrap();
+                let right = Suspended::new(right, self.right_io.take());
+                Ok(Either::Left((output, right)))
+            }
+            Some(Either::Right(output)) => {
+                debug!("right coroutine terminated first");
+                let (left, _) = self.coroutines.take().unwrap();
+                let left = Suspended::new(left, self.left_io.take());
+                Ok(Either::Right((left, output)))
+            }
+            None => Err(request(self.left_io.take(), self.right_io.take())),
+        }
+    }
+}
+
+impl<L: Coroutine, R: Coroutine> Coroutine for Select<L, R> {
+    type Output = Either<(L::Output, Suspended<R>), (Suspended<L>, R::Output)>;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Select::resume(self, arg)
+    }
+}
+
+/// A coroutine that lost a [`Select`] race.
+///
+/// The request the coroutine emitted during the race, if any, was not
+/// processed. Resuming the suspended coroutine without argument emits
+/// that request again, so that the coroutine can be run to
+/// termination on its own.
+#[derive(Debug)]
+pub struct Suspended<C> {
+    coroutine: C,
+    io: Option<Box<Io>>,
+}
+
+impl<C> Suspended<C> {
+    fn new(coroutine: C, io: Option<Box<Io>>) -> Self {
+        Self { coroutine, io }
+    }
+
+    /// Consumes the suspended coroutine and returns the inner one,
+    /// together with its unprocessed request.
+    pub fn into_parts(self) -> (C, Option<Io>) {
+        (self.coroutine, self.io.map(|io| *io))
+    }
+}
+
+impl<C: Coroutine> Coroutine for Suspended<C> {
+    type Output = C::Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        match (arg, self.io.take()) {
+            (None, Some(io)) => {
+                debug!("emit request suspended by select");
+                Err(*io)
+            }
+            (arg, _) => self.coroutine.resume(arg),
+        }
+    }
+}
+
+/// Builds the select request of the given requests, routing write
+/// requests to the write slots of their stream.
+pub(super) fn request(left: Option<Box<Io>>, right: Option<Box<Io>>) -> Io {
+    let (left, left_write) = route(left);
+    let (right, right_write) = route(right);
+
+    Io::Select(Err(SelectRequest {
+        left,
+        right,
+        left_write,
+        right_write,
+    }))
+}
+
+/// Splits the given request into its read and write slots.
+fn route(io: Option<Box<Io>>) -> (Option<Box<Io>>, Option<Box<Io>>) {
+    fn is_write(io: &Io) -> bool {
+        match io {
+            Io::Write(_) | Io::WriteVectored(_) | Io::Flush(_) | Io::Shutdown(_) => true,
+            Io::Timeout(Err((_, io))) => is_write(io),
+            _ => false,
+        }
+    }
+
+    match io {
+        Some(io) if is_write(&io) => (None, Some(io)),
+        io => (io, None),
+    }
+}
+
+/// Resumes the given coroutine, keeping its next request in the
+/// given slot.
+///
+/// Errors emitted by the coroutine are emitted as is, since they do
+/// not target any stream.
+pub(super) fn resume<C: Coroutine>(
+    coroutine: &mut C,
+    slot: &mut Option<Box<Io>>,
+    arg: Option<Io>,
+) -> Result<Option<C::Output>, Io> {
+    match coroutine.resume(arg) {
+        Ok(output) => Ok(Some(output)),
+        Err(Io::Error(err)) => Err(Io::Error(err)),
+        Err(io) => {
+            *slot = Some(Box::new(io));
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{ReadLine, WriteAll},
+        runtimes::mock::{run, run_select, Error, MockStream},
+        Error::BufferNotReady,
+    };
+
+    use super::{Either, Select};
+
+    #[test]
+    fn first_ready() {
+        let mut left = MockStream::new().pending().read("left\n");
+        let mut right = MockStream::new().read("right\n");
+
+        let select = Select::new(ReadLine::new(), ReadLine::new());
+        let output = run_select(&mut left, &mut right, select).unwrap();
+
+        let Either::Right((left_read, line)) = output else {
+            panic!("expected right coroutine to terminate first");
+        };
+
+        assert_eq!(line, b"right");
+        assert!(right.is_done());
+
+        // the losing coroutine keeps its request, and can be resumed
+        assert_eq!(run(&mut left, left_read), Ok(b"left".to_vec()));
+        assert!(left.is_done());
+    }
+
+    #[test]
+    fn first_terminated() {
+        let mut left = MockStream::new().read("lef").pending().read("t\n");
+        let mut right = MockStream::new().pending().write("ping");
+
+        let select = Select::new(ReadLine::new(), WriteAll::new(*b"ping"));
+        let output = run_select(&mut left, &mut right, select).unwrap();
+
+        let Either::Right((left_read, count)) = output else {
+            panic!("expected right coroutine to terminate first");
+        };
+
+        assert_eq!(count, 4);
+        assert_eq!(left.steps().count(), 1);
+
+        assert_eq!(run(&mut left, left_read), Ok(b"left".to_vec()));
+    }
+
+    #[test]
+    fn resumed_after_termination() {
+        let mut left = MockStream::new().read("left\n");
+        let mut right = MockStream::new();
+
+        let mut select = Select::new(ReadLine::new(), WriteAll::new(*b"ping"));
+        let output = run_select(&mut left, &mut right, &mut select).unwrap();
+        assert!(matches!(output, Either::Left((ref line, _)) if line == b"left"));
+
+        let err = run_select(&mut left, &mut right, select).unwrap_err();
+        assert_eq!(err, Error::Coroutine(BufferNotReady));
+    }
+}
@@ -0,0 +1,50 @@
+//! Module dedicated to the [`Shutdown`] I/O-free coroutine.
+
+use alloc::boxed::Box;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+/// I/O-free coroutine for shutting down the write half of a stream.
+///
+/// Shutting down signals the end of the request to the peer, which
+/// then reads EOF. The read half of the stream stays open.
+#[derive(Debug, Default)]
+pub struct Shutdown;
+
+impl Shutdown {
+    /// Creates a new coroutine to shut down a stream.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Makes the shutdown progress.
+    pub fn resume(&mut self, arg: Option<Io>) -> Result<(), Io> {
+        let Some(arg) = arg else {
+            debug!("break: need I/O to shut down stream");
+            return Err(Io::Shutdown(Err(())));
+        };
+
+        debug!("resume after shutting down stream");
+
+        let Io::Shutdown(Ok(())) = arg else {
+            let got = Box::new(arg);
+            return Err(Error::UnexpectedIo {
+                expected: "shutdown",
+                got,
+            }
+            .into());
+        };
+
+        Ok(())
+    }
+}
+
+impl Coroutine for Shutdown {
+    type Output = ();
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Shutdown::resume(self, arg)
+    }
+}
@@ -0,0 +1,58 @@
+//! Module dedicated to the [`Then`] coroutine combinator.
+
+use log::debug;
+
+use crate::{Coroutine, Io};
+
+/// Coroutine combinator sequencing two coroutines.
+///
+/// See [`Coroutine::then`].
+#[derive(Debug)]
+pub struct Then<C, D> {
+    first: Option<C>,
+    second: D,
+}
+
+impl<C, D> Then<C, D> {
+    /// Creates a new combinator resuming the given coroutines one
+    /// after the other.
+    pub fn new(first: C, second: D) -> Self {
+        Self {
+            first: Some(first),
+            second,
+        }
+    }
+}
+
+impl<C: Coroutine, D: Coroutine> Coroutine for Then<C, D> {
+    type Output = D::Output;
+
+    fn resume(&mut self, mut arg: Option<Io>) -> Result<Self::Output, Io> {
+        if let Some(first) = &mut self.first {
+            first.resume(arg.take())?;
+            debug!("first coroutine terminated, resuming the second one");
+            self.first = None;
+        }
+
+        self.second.resume(arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{ReadLine, WriteAll},
+        runtimes::mock::{run, MockStream},
+        Coroutine,
+    };
+
+    #[test]
+    fn request_response() {
+        let mut stream = MockStream::new().write("A1 NOOP\r\n").read("A1 OK\r\n");
+
+        let exchange = WriteAll::new(*b"A1 NOOP\r\n").then(ReadLine::new());
+
+        assert_eq!(run(&mut stream, exchange), Ok(b"A1 OK".to_vec()));
+        assert!(stream.is_done());
+    }
+}
@@ -0,0 +1,156 @@
+//! Module dedicated to the [`Timeout`] coroutine combinator.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io, TimeoutOutput};
+
+/// Coroutine combinator bounding in time a coroutine.
+///
+/// Requests are wrapped into [`Io::Timeout`], and responses are
+/// unwrapped before resuming the inner coroutine. The duration is a
+/// budget shared by all the requests: each request is sent with the
+/// time left, as reported by the runtime in [`TimeoutOutput::Done`].
+///
+/// If the budget runs out, the combinator emits [`Error::TimedOut`]
+/// and keeps the request: resuming it without argument emits the same
+/// request again, with a fresh budget.
+///
+/// See [`Coroutine::timeout`].
+#[derive(Debug)]
+pub struct Timeout<C> {
+    coroutine: C,
+    timeout: Duration,
+    remaining: Duration,
+    timed_out: Option<Box<Io>>,
+}
+
+impl<C> Timeout<C> {
+    /// Creates a new combinator bounding the given coroutine with the
+    /// given duration.
+    pub fn new(coroutine: C, timeout: Duration) -> Self {
+        Self {
+            coroutine,
+            timeout,
+            remaining: timeout,
+            timed_out: None,
+        }
+    }
+
+    /// Keeps the given request, and emits the timed out error.
+    fn time_out(&mut self, io: Box<Io>) -> Io {
+        debug!("request timed out after {:?}", self.timeout);
+        self.timed_out = Some(io);
+        self.remaining = self.timeout;
+        let timeout = self.timeout;
+        Error::TimedOut { timeout }.into()
+    }
+
+    /// Consumes the combinator and returns the inner coroutine.
+    pub fn into_inner(self) -> C {
+        self.coroutine
+    }
+}
+
+impl<C: Coroutine> Coroutine for Timeout<C> {
+    type Output = C::Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        let arg = match arg {
+            Some(Io::Timeout(Ok(TimeoutOutput::Done(io, elapsed)))) => {
+                self.remaining = self.remaining.saturating_sub(elapsed);
+                Some(*io)
+            }
+            Some(Io::Timeout(Ok(TimeoutOutput::Elapsed(io)))) => {
+                return Err(self.time_out(io));
+            }
+            None => match self.timed_out.take() {
+                Some(io) => {
+                    debug!("retry timed out request");
+                    return Err(Io::Timeout(Err((self.remaining, io))));
+                }
+                None => None,
+            },
+            arg => arg,
+        };
+
+        match self.coroutine.resume(arg) {
+            Ok(output) => Ok(output),
+            Err(Io::Error(err)) => Err(Io::Error(err)),
+            Err(io) if self.remaining.is_zero() => Err(self.time_out(Box::new(io))),
+            Err(io) => Err(Io::Timeout(Err((self.remaining, Box::new(io))))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::{
+        coroutines::{ReadLine, WriteAll},
+        runtimes::mock::{run, Error, MockStream},
+        Coroutine,
+        Error::TimedOut,
+    };
+
+    #[test]
+    fn within_timeout() {
+        let mut stream = MockStream::new().write("A1 NOOP\r\n").read("A1 OK\r\n");
+        let timeout = Duration::from_secs(30);
+
+        let exchange = WriteAll::new(*b"A1 NOOP\r\n")
+            .then(ReadLine::new())
+            .timeout(timeout);
+
+        assert_eq!(run(&mut stream, exchange), Ok(b"A1 OK".to_vec()));
+    }
+
+    #[test]
+    fn timed_out() {
+        let mut stream = MockStream::new().write("A1 NOOP\r\n").timeout();
+        let timeout = Duration::from_secs(30);
+
+        let exchange = WriteAll::new(*b"A1 NOOP\r\n")
+            .then(ReadLine::new())
+            .timeout(timeout);
+
+        let err = run(&mut stream, exchange).unwrap_err();
+        assert_eq!(err, Error::Coroutine(TimedOut { timeout }));
+    }
+
+    #[test]
+    fn deadline() {
+        let mut stream = MockStream::new()
+            .delay(Duration::from_secs(10))
+            .read("A1 ")
+            .delay(Duration::from_secs(10))
+            .read("O")
+            .delay(Duration::from_secs(10))
+            .read("K\r\n");
+        let timeout = Duration::from_secs(25);
+        let mut exchange = ReadLine::new().timeout(timeout);
+
+        let err = run(&mut stream, &mut exchange).unwrap_err();
+        assert_eq!(err, Error::Coroutine(TimedOut { timeout }));
+
+        // the last read is retried with a fresh budget
+        assert_eq!(run(&mut stream, exchange), Ok(b"A1 OK".to_vec()));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn retry() {
+        let mut stream = MockStream::new().timeout().read("A1 OK\r\n");
+        let timeout = Duration::from_secs(30);
+        let mut exchange = ReadLine::new().timeout(timeout);
+
+        let err = run(&mut stream, &mut exchange).unwrap_err();
+        assert_eq!(err, Error::Coroutine(TimedOut { timeout }));
+
+        assert_eq!(run(&mut stream, exchange), Ok(b"A1 OK".to_vec()));
+        assert!(stream.is_done());
+    }
+}
@@ -0,0 +1,90 @@
+//! Module dedicated to the [`WriteAll`] I/O-free coroutine.
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+use super::Write;
+
+/// I/O-free coroutine for writing all bytes into a stream.
+///
+/// Unlike [`Write`], this coroutine keeps writing the remaining bytes
+/// until the whole buffer is accepted by the stream.
+#[derive(Debug, Default)]
+pub struct WriteAll {
+    write: Write,
+    count: usize,
+}
+
+impl WriteAll {
+    /// Creates a new coroutine to write all the given bytes.
+    pub fn new(bytes: impl IntoIterator<Item = u8>) -> Self {
+        Self {
+            write: Write::new(bytes),
+            count: 0,
+        }
+    }
+
+    /// Makes the write progress.
+    ///
+    /// Returns the total amount of bytes written.
+    pub fn resume(&mut self, mut arg: Option<Io>) -> Result<usize, Io> {
+        loop {
+            let mut output = self.write.resume(arg.take())?;
+
+            let n = output.bytes_count;
+            let len = output.buffer.len();
+            self.count += n;
+
+            if n >= len {
+                debug!("wrote all {} bytes", self.count);
+                break Ok(self.count);
+            }
+
+            if n == 0 {
+                debug!("expected {len} more bytes to be written, wrote 0");
+                break Err(Error::WriteZero { remaining: len }.into());
+            }
+
+            debug!("wrote {n}/{len} bytes, write remaining ones");
+            output.buffer.drain(..n);
+            self.write.replace(output.buffer);
+        }
+    }
+}
+
+impl Coroutine for WriteAll {
+    type Output = usize;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        WriteAll::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtimes::mock::{run, Error, MockStream},
+        Error::WriteZero,
+    };
+
+    use super::WriteAll;
+
+    #[test]
+    fn write_all_partial() {
+        let mut stream = MockStream::new().write("abcd").write("ef");
+        let output = run(&mut stream, WriteAll::new(b"abcdef".to_vec()));
+
+        assert_eq!(output, Ok(6));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn write_all_zero() {
+        let mut stream = MockStream::new().write("");
+        let err = run(&mut stream, WriteAll::new(b"abcdef".to_vec()));
+
+        let expected = WriteZero { remaining: 6 };
+        assert_eq!(err, Err(Error::Coroutine(expected)));
+    }
+}
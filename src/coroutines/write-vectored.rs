@@ -0,0 +1,158 @@
+//! Module dedicated to the [`WriteVectored`] I/O-free coroutine.
+
+use alloc::{boxed::Box, vec::Vec};
+
+use log::debug;
+
+use crate::{Coroutine, Error, Io};
+
+/// I/O-free coroutine for writing multiple buffers into a stream.
+///
+/// The buffers are handed to the runtime as they are, without being
+/// concatenated first. Partial writes are handled: the coroutine
+/// keeps writing the remaining bytes until all buffers are written.
+#[derive(Debug, Default)]
+pub struct WriteVectored {
+    buffers: Option<Vec<Vec<u8>>>,
+    count: usize,
+}
+
+impl WriteVectored {
+    /// Creates a new coroutine to write all the given buffers.
+    pub fn new(buffers: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        let buffers: Vec<Vec<u8>> = buffers
+            .into_iter()
+            .filter(|buffer| !buffer.is_empty())
+            .collect();
+
+        let n: usize = buffers.iter().map(Vec::len).sum();
+        let len = buffers.len();
+        debug!("prepare {n} bytes from {len} buffers to be written");
+
+        Self {
+            buffers: Some(buffers),
+            count: 0,
+        }
+    }
+
+    /// Adds the given buffer to the buffers to be written.
+    pub fn push(&mut self, buffer: Vec<u8>) {
+        if buffer.is_empty() {
+            return;
+        }
+
+        match &mut self.buffers {
+            Some(buffers) => buffers.push(buffer),
+            None => *self = Self::new([buffer]),
+        }
+    }
+
+    /// Makes the write progress.
+    ///
+    /// Returns the total amount of bytes written.
+    pub fn resume(&mut self, mut arg: Option<Io>) -> Result<usize, Io> {
+        loop {
+            let Some(arg) = arg.take() else {
+                let Some(buffers) = self.buffers.take() else {
+                    return Err(Error::BufferNotReady.into());
+                };
+
+                if buffers.is_empty() {
+                    debug!("wrote all {} bytes", self.count);
+                    return Ok(self.count);
+                }
+
+                debug!("break: need I/O to write buffers");
+                return Err(Io::WriteVectored(Err(buffers)));
+            };
+
+            debug!("resume after writting buffers");
+
+            let Io::WriteVectored(Ok(output)) = arg else {
+                let got = Box::new(arg);
+                return Err(Error::UnexpectedIo {
+                    expected: "vectored write",
+                    got,
+                }
+                .into());
+            };
+
+            let mut n = output.bytes_count;
+            let mut buffers = output.buffers;
+            let remaining: usize = buffers.iter().map(Vec::len).sum();
+
+            if n == 0 {
+                debug!("expected {remaining} more bytes to be written, wrote 0");
+                return Err(Error::WriteZero { remaining }.into());
+            }
+
+            debug!("wrote {n}/{remaining} bytes");
+            self.count += n;
+
+            // removes fully written buffers, then the written bytes
+            // of the partially written one
+            let written = buffers.iter().take_while(|buffer| {
+                let fully_written = buffer.len() <= n;
+
+                if fully_written {
+                    n -= buffer.len();
+                }
+
+                fully_written
+            });
+
+            let written = written.count();
+            buffers.drain(..written);
+
+            if let Some(buffer) = buffers.first_mut() {
+                buffer.drain(..n);
+            }
+
+            self.buffers = Some(buffers);
+        }
+    }
+}
+
+impl Coroutine for WriteVectored {
+    type Output = usize;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        WriteVectored::resume(self, arg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        runtimes::mock::{run, Error, MockStream},
+        Error::WriteZero,
+    };
+
+    use super::WriteVectored;
+
+    #[test]
+    fn write_vectored_partial() {
+        let mut stream = MockStream::new()
+            .write("hea")
+            .write("dbo")
+            .write("dyt")
+            .write("rai")
+            .write("ler");
+
+        let buffers = [b"head".to_vec(), Vec::new(), b"body".to_vec()];
+        let mut write = WriteVectored::new(buffers);
+        write.push(b"trailer".to_vec());
+
+        assert_eq!(run(&mut stream, write), Ok(15));
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn write_vectored_zero() {
+        let mut stream = MockStream::new().write("headbo").write("");
+        let write = WriteVectored::new([b"head".to_vec(), b"body".to_vec()]);
+
+        let expected = WriteZero { remaining: 2 };
+        assert_eq!(run(&mut stream, write), Err(Error::Coroutine(expected)));
+    }
+}
@@ -1,8 +1,10 @@
 //! Module dedicated to the [`Write`] I/O-free coroutine.
 
+use alloc::{boxed::Box, vec::Vec};
+
 use log::debug;
 
-use crate::{Io, Output};
+use crate::{Coroutine, Error, Io, Output};
 
 /// I/O-free coroutine for writing bytes into a stream.
 #[derive(Debug, Default)]
@@ -42,7 +44,7 @@ impl Write {
     pub fn resume(&mut self, arg: Option<Io>) -> Result<Output, Io> {
         let Some(arg) = arg else {
             let Some(bytes) = self.bytes.take() else {
-                return Err(Io::err("write bytes not ready"));
+                return Err(Error::BufferNotReady.into());
             };
 
             debug!("break: need I/O to write bytes");
@@ -52,8 +54,12 @@ impl Write {
         debug!("resume after writting bytes");
 
         let Io::Write(Ok(output)) = arg else {
-            let msg = format!("expected write output, got {arg:?}");
-            return Err(Io::err(msg));
+            let got = Box::new(arg);
+            return Err(Error::UnexpectedIo {
+                expected: "write",
+                got,
+            }
+            .into());
         };
 
         let n = output.bytes_count;
@@ -62,3 +68,11 @@ impl Write {
         Ok(output)
     }
 }
+
+impl Coroutine for Write {
+    type Output = Output;
+
+    fn resume(&mut self, arg: Option<Io>) -> Result<Self::Output, Io> {
+        Write::resume(self, arg)
+    }
+}
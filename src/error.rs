@@ -0,0 +1,91 @@
+//! Module dedicated to the stream [`Error`].
+
+use alloc::{boxed::Box, string::String};
+use core::{fmt, time::Duration};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::Io;
+
+/// The streams error enum, emitted by [coroutines] via [`Io::Error`].
+///
+/// [coroutines]: crate::coroutines
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The stream reached EOF before the expected amount of bytes.
+    UnexpectedEof { expected: usize, got: usize },
+
+    /// The coroutine was resumed with an I/O response that does not
+    /// match the I/O request it emitted.
+    UnexpectedIo {
+        expected: &'static str,
+        got: Box<Io>,
+    },
+
+    /// The coroutine was resumed while its buffer was owned by the
+    /// runtime, or after it terminated.
+    BufferNotReady,
+
+    /// The stream accepted 0 bytes while some were remaining.
+    WriteZero { remaining: usize },
+
+    /// A line exceeded the maximum allowed length.
+    LineTooLong { max_len: usize },
+
+    /// The stream sent more bytes than the given limit.
+    LimitExceeded { limit: usize },
+
+    /// The stream did not process a request within the given
+    /// duration.
+    TimedOut { timeout: Duration },
+
+    /// The bytes read do not form a valid frame.
+    ///
+    /// This variant is meant to be used by protocol coroutines built
+    /// on the top of this library.
+    InvalidFrame(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof { expected, got } => {
+                write!(f, "expected {expected} bytes, got {got} then EOF")
+            }
+            Self::UnexpectedIo { expected, got } => {
+                write!(f, "expected {expected} output, got {got:?}")
+            }
+            Self::BufferNotReady => write!(f, "buffer not ready"),
+            Self::WriteZero { remaining } => {
+                write!(f, "wrote 0 bytes while {remaining} were remaining")
+            }
+            Self::LineTooLong { max_len } => {
+                write!(f, "line exceeds {max_len} bytes")
+            }
+            Self::LimitExceeded { limit } => {
+                write!(f, "stream exceeds {limit} bytes")
+            }
+            Self::TimedOut { timeout } => write!(f, "timed out after {timeout:?}"),
+            Self::InvalidFrame(reason) => write!(f, "invalid frame: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        let kind = match err {
+            Error::UnexpectedEof { .. } => io::ErrorKind::UnexpectedEof,
+            Error::WriteZero { .. } => io::ErrorKind::WriteZero,
+            Error::TimedOut { .. } => io::ErrorKind::TimedOut,
+            Error::LineTooLong { .. } | Error::LimitExceeded { .. } | Error::InvalidFrame(_) => {
+                io::ErrorKind::InvalidData
+            }
+            Error::UnexpectedIo { .. } | Error::BufferNotReady => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, err)
+    }
+}
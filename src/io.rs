@@ -1,4 +1,7 @@
-use std::fmt;
+use alloc::{boxed::Box, vec::Vec};
+use core::time::Duration;
+
+use crate::Error;
 
 /// The streams I/O request enum, emitted by [coroutines] and
 /// processed by [runtimes].
@@ -10,15 +13,35 @@ use std::fmt;
 /// [runtimes]: crate::runtimes
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Io {
-    Error(String),
+    Error(Error),
     Read(Result<Output, Vec<u8>>),
     Write(Result<Output, Vec<u8>>),
+    WriteVectored(Result<VectoredOutput, Vec<Vec<u8>>>),
+
+    /// Request to flush the stream (`Err`), and its response (`Ok`).
+    Flush(Result<(), ()>),
+
+    /// Request to shut down the write half of the stream (`Err`), and
+    /// its response (`Ok`).
+    Shutdown(Result<(), ()>),
+
+    /// Request to process the inner request within the given duration
+    /// (`Err`), and its response (`Ok`).
+    ///
+    /// See [`TimeoutOutput`] for the response.
+    Timeout(Result<TimeoutOutput, (Duration, Box<Io>)>),
+
+    /// Request to process whichever of the inner requests targets the
+    /// first ready stream (`Err`), and its response (`Ok`).
+    ///
+    /// See [`SelectRequest`] for the request, and [`SelectOutput`]
+    /// for the response.
+    Select(Result<SelectOutput, SelectRequest>),
 }
 
-impl Io {
-    pub fn err(message: impl fmt::Display) -> Io {
-        let message = format!("stream error: {message}");
-        Io::Error(message.to_string())
+impl From<Error> for Io {
+    fn from(err: Error) -> Self {
+        Io::Error(err)
     }
 }
 
@@ -33,3 +56,94 @@ impl Output {
         &self.buffer[..self.bytes_count]
     }
 }
+
+/// The stream targeted by a request of an [`Io::Select`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// The request of an [`Io::Select`].
+///
+/// It contains at most one request per stream, tagged by the side of
+/// the stream it targets. A stream can also be targeted by a write
+/// request (write, vectored write, flush or shutdown) alongside a read
+/// request, so that both directions of the stream progress
+/// concurrently.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SelectRequest {
+    pub left: Option<Box<Io>>,
+    pub right: Option<Box<Io>>,
+
+    /// The write request targeting the left stream, processed
+    /// concurrently with the read request of [`SelectRequest::left`].
+    pub left_write: Option<Box<Io>>,
+
+    /// The write request targeting the right stream, processed
+    /// concurrently with the read request of [`SelectRequest::right`].
+    pub right_write: Option<Box<Io>>,
+}
+
+impl SelectRequest {
+    /// Returns `true` if the request contains no inner request.
+    pub fn is_empty(&self) -> bool {
+        self.left.is_none()
+            && self.right.is_none()
+            && self.left_write.is_none()
+            && self.right_write.is_none()
+    }
+
+    /// Returns the inner requests, tagged by the side of the stream
+    /// they target.
+    pub fn slots_mut(&mut self) -> [(Side, &mut Option<Box<Io>>); 4] {
+        let Self {
+            left,
+            right,
+            left_write,
+            right_write,
+        } = self;
+
+        [
+            (Side::Left, left),
+            (Side::Left, left_write),
+            (Side::Right, right),
+            (Side::Right, right_write),
+        ]
+    }
+}
+
+/// The output of an [`Io::Select`].
+///
+/// It contains the response of the processed request, and gives back
+/// the other requests untouched. A read response comes from
+/// [`SelectRequest::left`] or [`SelectRequest::right`], any other
+/// response comes from the matching write request if there was one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SelectOutput {
+    pub side: Side,
+    pub io: Box<Io>,
+    pub pending: SelectRequest,
+}
+
+/// The output of an [`Io::Timeout`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TimeoutOutput {
+    /// The inner request was processed in time, with its response and
+    /// the time it took.
+    Done(Box<Io>, Duration),
+
+    /// The duration elapsed before the inner request was processed,
+    /// which is given back untouched.
+    Elapsed(Box<Io>),
+}
+
+/// The output of a vectored write.
+///
+/// The bytes count is the total amount of bytes written, across all
+/// buffers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VectoredOutput {
+    pub buffers: Vec<Vec<u8>>,
+    pub bytes_count: usize,
+}
@@ -1,9 +1,18 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+extern crate alloc;
+
+mod coroutine;
 pub mod coroutines;
+mod error;
 mod io;
 pub mod runtimes;
 
 #[doc(inline)]
-pub use self::io::{Io, Output};
+pub use self::{
+    coroutine::Coroutine,
+    error::Error,
+    io::{Io, Output, SelectOutput, SelectRequest, Side, TimeoutOutput, VectoredOutput},
+};
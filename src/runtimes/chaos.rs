@@ -0,0 +1,404 @@
+//! Module dedicated to the fault-injection runtime wrapper.
+//!
+//! The [`Chaos`] wrapper sits between coroutines and any runtime
+//! handler, and randomly alters [`Io`] requests under a seed: reads
+//! are truncated to 1 byte, writes are made partial, errors are
+//! injected and reads return early EOF. It is meant to harden
+//! coroutines against behaviours real streams rarely exhibit in
+//! tests.
+
+use std::{future::Future, io};
+
+use log::debug;
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+use super::Error;
+
+/// The error kinds injected by [`Chaos::errors`].
+const ERROR_KINDS: [io::ErrorKind; 3] = [
+    io::ErrorKind::Interrupted,
+    io::ErrorKind::WouldBlock,
+    io::ErrorKind::ConnectionReset,
+];
+
+/// The runtime handler wrapper, injecting faults under a seed.
+///
+/// All fault probabilities default to 0. The same seed with the same
+/// probabilities always injects the same faults.
+#[derive(Clone, Debug)]
+pub struct Chaos {
+    state: u64,
+    short_reads: f64,
+    partial_writes: f64,
+    errors: f64,
+    early_eof: f64,
+}
+
+/// The fault decided before processing a request.
+enum Fault {
+    /// The request is answered without reaching the stream.
+    Respond(Result<Io, Error>),
+
+    /// The request is forwarded to the stream, then its response is
+    /// restored.
+    Forward(Io, Restore),
+}
+
+/// The request altered before being forwarded.
+enum Restore {
+    Nothing,
+    Read(usize),
+    Write(Vec<u8>),
+    WriteVectored(Vec<Vec<u8>>),
+}
+
+impl Chaos {
+    /// Creates a new fault injector with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift state must not be zero
+            state: seed.max(1),
+            short_reads: 0.0,
+            partial_writes: 0.0,
+            errors: 0.0,
+            early_eof: 0.0,
+        }
+    }
+
+    /// Changes the probability for a read to be truncated to 1 byte.
+    pub fn short_reads(mut self, probability: f64) -> Self {
+        self.short_reads = probability;
+        self
+    }
+
+    /// Changes the probability for a write to be partial.
+    pub fn partial_writes(mut self, probability: f64) -> Self {
+        self.partial_writes = probability;
+        self
+    }
+
+    /// Changes the probability for a read or a write to fail with
+    /// [`io::ErrorKind::Interrupted`], [`io::ErrorKind::WouldBlock`]
+    /// or [`io::ErrorKind::ConnectionReset`].
+    pub fn errors(mut self, probability: f64) -> Self {
+        self.errors = probability;
+        self
+    }
+
+    /// Changes the probability for a read to return EOF.
+    pub fn early_eof(mut self, probability: f64) -> Self {
+        self.early_eof = probability;
+        self
+    }
+
+    /// Processes the given request with the given runtime handler,
+    /// possibly injecting a fault.
+    pub fn handle(
+        &mut self,
+        io: Io,
+        handle: impl FnOnce(Io) -> Result<Io, Error>,
+    ) -> Result<Io, Error> {
+        match self.inject(io) {
+            Fault::Respond(output) => output,
+            Fault::Forward(io, restore) => match handle(io) {
+                Ok(io) => Self::restore(io, restore),
+                Err(err) => Err(Self::restore_error(err, restore)),
+            },
+        }
+    }
+
+    /// Async version of [`Chaos::handle`], for async runtime handlers.
+    pub async fn handle_async<F>(
+        &mut self,
+        io: Io,
+        handle: impl FnOnce(Io) -> F,
+    ) -> Result<Io, Error>
+    where
+        F: Future<Output = Result<Io, Error>>,
+    {
+        match self.inject(io) {
+            Fault::Respond(output) => output,
+            Fault::Forward(io, restore) => match handle(io).await {
+                Ok(io) => Self::restore(io, restore),
+                Err(err) => Err(Self::restore_error(err, restore)),
+            },
+        }
+    }
+
+    /// Runs the given coroutine until it terminates.
+    ///
+    /// Every [`Io`] request emitted by the coroutine is processed by
+    /// [`Chaos::handle`], and its response is used to resume the
+    /// coroutine.
+    pub fn run<C: Coroutine>(
+        &mut self,
+        mut coroutine: C,
+        mut handle: impl FnMut(Io) -> Result<Io, Error>,
+    ) -> Result<C::Output, Error> {
+        let mut arg = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                Ok(output) => break Ok(output),
+                Err(io) => arg = Some(self.handle(io, &mut handle)?),
+            }
+        }
+    }
+
+    fn inject(&mut self, io: Io) -> Fault {
+        let is_stream_io = matches!(
+            io,
+            Io::Read(Err(_)) | Io::Write(Err(_)) | Io::WriteVectored(Err(_))
+        );
+
+        if is_stream_io && self.chance(self.errors) {
+            let kind = ERROR_KINDS[self.below(ERROR_KINDS.len())];
+            debug!("inject {kind:?} error");
+            return Fault::Respond(Err(Error::new(io, kind.into())));
+        }
+
+        match io {
+            Io::Read(Err(buffer)) if self.chance(self.early_eof) => {
+                debug!("inject early EOF");
+                let output = Output {
+                    buffer,
+                    bytes_count: 0,
+                };
+                Fault::Respond(Ok(Io::Read(Ok(output))))
+            }
+            Io::Read(Err(mut buffer)) if buffer.len() > 1 && self.chance(self.short_reads) => {
+                debug!("inject short read");
+                let len = buffer.len();
+                buffer.truncate(1);
+                Fault::Forward(Io::Read(Err(buffer)), Restore::Read(len))
+            }
+            Io::Write(Err(buffer)) if buffer.len() > 1 && self.chance(self.partial_writes) => {
+                let len = 1 + self.below(buffer.len() - 1);
+                debug!("inject partial write of {len} bytes");
+                let partial = buffer[..len].to_vec();
+                Fault::Forward(Io::Write(Err(partial)), Restore::Write(buffer))
+            }
+            Io::WriteVectored(Err(buffers)) if self.chance(self.partial_writes) => {
+                let total: usize = buffers.iter().map(Vec::len).sum();
+
+                if total < 2 {
+                    return Fault::Forward(Io::WriteVectored(Err(buffers)), Restore::Nothing);
+                }
+
+                let mut len = 1 + self.below(total - 1);
+                debug!("inject partial vectored write of {len} bytes");
+
+                let mut partial = Vec::new();
+
+                for buffer in &buffers {
+                    let n = len.min(buffer.len());
+                    partial.push(buffer[..n].to_vec());
+                    len -= n;
+                }
+
+                let io = Io::WriteVectored(Err(partial));
+                Fault::Forward(io, Restore::WriteVectored(buffers))
+            }
+            io => Fault::Forward(io, Restore::Nothing),
+        }
+    }
+
+    fn restore(io: Io, restore: Restore) -> Result<Io, Error> {
+        let io = match (io, restore) {
+            (Io::Read(Ok(mut output)), Restore::Read(len)) => {
+                output.buffer.resize(len, 0);
+                Io::Read(Ok(output))
+            }
+            (Io::Write(Ok(output)), Restore::Write(buffer)) => Io::Write(Ok(Output {
+                buffer,
+                bytes_count: output.bytes_count,
+            })),
+            (Io::WriteVectored(Ok(output)), Restore::WriteVectored(buffers)) => {
+                Io::WriteVectored(Ok(VectoredOutput {
+                    buffers,
+                    bytes_count: output.bytes_count,
+                }))
+            }
+            (io, _) => io,
+        };
+
+        Ok(io)
+    }
+
+    /// Restores the request given back by the given error, so that
+    /// the caller does not resume the coroutine with the altered one.
+    fn restore_error(err: Error, restore: Restore) -> Error {
+        let (io, err) = match err.into_parts() {
+            (Some(io), err) => (io, err),
+            (None, err) => return err.into(),
+        };
+
+        let io = match (io, restore) {
+            (Io::Read(Err(mut buffer)), Restore::Read(len)) => {
+                buffer.resize(len, 0);
+                Io::Read(Err(buffer))
+            }
+            (Io::Write(Err(_)), Restore::Write(buffer)) => Io::Write(Err(buffer)),
+            (Io::WriteVectored(Err(_)), Restore::WriteVectored(buffers)) => {
+                Io::WriteVectored(Err(buffers))
+            }
+            (io, _) => io,
+        };
+
+        Error::new(io, err)
+    }
+
+    /// Returns `true` with the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+
+        let n = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        n < probability
+    }
+
+    /// Returns a number lower than the given one.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns the next pseudo-random number (xorshift64*).
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+
+    use crate::{
+        coroutines::{ReadExact, ReadToEnd, WriteAll, WriteVectored},
+        runtimes::{std::handle, Error},
+        Io,
+    };
+
+    use super::Chaos;
+
+    #[test]
+    fn short_reads_and_partial_writes() {
+        for seed in 0..32 {
+            let mut chaos = Chaos::new(seed).short_reads(0.5).partial_writes(0.5);
+
+            let mut stream = Cursor::new(b"abcdefghijklmnop".to_vec());
+            let read = ReadExact::with_capacity(8, 12);
+            let output = chaos.run(read, |io| handle(&mut stream, io)).unwrap();
+            assert_eq!(output, b"abcdefghijkl");
+
+            let mut stream = Cursor::new(Vec::new());
+            let write = WriteAll::new(*b"request");
+            chaos.run(write, |io| handle(&mut stream, io)).unwrap();
+            let write = WriteVectored::new([b" vec".to_vec(), b"tored".to_vec()]);
+            chaos.run(write, |io| handle(&mut stream, io)).unwrap();
+            assert_eq!(stream.into_inner(), b"request vectored");
+        }
+    }
+
+    #[test]
+    fn same_seed_same_faults() {
+        let reads = |seed| {
+            let mut chaos = Chaos::new(seed).short_reads(0.5);
+            let mut stream = Cursor::new(b"abcdefghijklmnop".to_vec());
+            let mut count = 0;
+
+            chaos
+                .run(ReadToEnd::with_capacity(4), |io| {
+                    count += 1;
+                    handle(&mut stream, io)
+                })
+                .unwrap();
+
+            count
+        };
+
+        assert_eq!(reads(42), reads(42));
+    }
+
+    #[test]
+    fn errors_and_early_eof() {
+        let mut chaos = Chaos::new(7).errors(1.0);
+        let mut stream = Cursor::new(b"abcdef".to_vec());
+
+        let err = chaos
+            .run(ReadExact::new(4), |io| handle(&mut stream, io))
+            .unwrap_err();
+
+        let kinds = [
+            io::ErrorKind::Interrupted,
+            io::ErrorKind::WouldBlock,
+            io::ErrorKind::ConnectionReset,
+        ];
+
+        assert!(kinds.contains(&err.kind()));
+
+        let mut chaos = Chaos::new(7).early_eof(1.0);
+        let err = chaos
+            .run(ReadExact::new(4), |io| handle(&mut stream, io))
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn restore_after_error() {
+        let reset = |io| Err(Error::new(io, io::ErrorKind::ConnectionReset.into()));
+
+        let mut chaos = Chaos::new(1).short_reads(1.0);
+        let err = chaos.handle(Io::Read(Err(vec![0; 4])), reset).unwrap_err();
+        assert_eq!(err.into_io(), Some(Io::Read(Err(vec![0; 4]))));
+
+        let mut chaos = Chaos::new(1).partial_writes(1.0);
+        let mut stream = Cursor::new(Vec::new());
+        let mut write = WriteAll::new(*b"request");
+
+        let io = write.resume(None).unwrap_err();
+        let err = chaos.handle(io, reset).unwrap_err();
+        let io = err.into_io().unwrap();
+        assert_eq!(io, Io::Write(Err(b"request".to_vec())));
+
+        let mut arg = Some(handle(&mut stream, io).unwrap());
+
+        while let Err(io) = write.resume(arg.take()) {
+            arg = Some(handle(&mut stream, io).unwrap());
+        }
+
+        assert_eq!(stream.into_inner(), b"request");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn handle_async() {
+        use crate::{coroutines::ReadExact, runtimes::tokio};
+
+        let mut chaos = Chaos::new(3).short_reads(1.0);
+        let mut stream = Cursor::new(b"abcdef".to_vec());
+        let mut read = ReadExact::new(4);
+        let mut arg = None;
+        let mut reads = 0;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                Ok(output) => break output,
+                Err(io) => {
+                    reads += 1;
+                    let stream = &mut stream;
+                    let handle = move |io| tokio::handle(stream, io);
+                    arg = Some(chaos.handle_async(io, handle).await.unwrap());
+                }
+            }
+        };
+
+        assert_eq!(output, b"abcd");
+        assert_eq!(reads, 4);
+    }
+}
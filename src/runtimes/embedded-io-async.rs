@@ -0,0 +1,214 @@
+//! Module dedicated to the [`embedded_io_async`]-based, async runtime.
+//!
+//! This runtime is the async counterpart of the [`embedded_io`]
+//! runtime, and can be driven by embedded executors like Embassy.
+//!
+//! [`embedded_io`]: super::embedded_io
+
+use alloc::vec::Vec;
+
+use embedded_io_async::{Error as _, ErrorKind, Read, Write};
+use log::debug;
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+pub use super::embedded_io::Error;
+
+/// The main runtime I/O handler.
+///
+/// This handler makes use of [`embedded_io_async`] traits to process
+/// stream [`Io`]. Interrupted operations are retried, and the request
+/// is given back with any other stream error (see [`Error::Stream`]).
+///
+/// Like the blocking runtime, [`Io::Shutdown`] and [`Io::Timeout`]
+/// are not supported, and [`Io::WriteVectored`] only writes the first
+/// buffer.
+pub async fn handle<S>(stream: S, io: Io) -> Result<Io, Error<S::Error>>
+where
+    S: Read + Write,
+{
+    match io {
+        Io::Error(err) => Err(Error::Coroutine(err)),
+        Io::Read(io) => read(stream, io).await,
+        Io::Write(io) => write(stream, io).await,
+        Io::WriteVectored(io) => write_vectored(stream, io).await,
+        Io::Flush(io) => flush(stream, io).await,
+        Io::Shutdown(_) => Err(Error::Unsupported("shutdown request")),
+        Io::Timeout(_) => Err(Error::Unsupported("timeout request")),
+        Io::Select(_) => Err(Error::Unsupported("select request")),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub async fn run<S, C>(mut stream: S, mut coroutine: C) -> Result<C::Output, Error<S::Error>>
+where
+    S: Read + Write,
+    C: Coroutine,
+{
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(&mut stream, io).await?),
+        }
+    }
+}
+
+pub async fn read<S: Read>(
+    mut stream: S,
+    input: Result<Output, Vec<u8>>,
+) -> Result<Io, Error<S::Error>> {
+    let Err(mut buffer) = input else {
+        return Err(Error::MissingInput("read buffer"));
+    };
+
+    debug!("reading bytes asynchronously");
+    let bytes_count = loop {
+        match stream.read(&mut buffer).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("read interrupted, retrying")
+            }
+            Err(err) => return Err(Error::Stream(err, Io::Read(Err(buffer)))),
+        }
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Read(Ok(output)))
+}
+
+pub async fn write<S: Write>(
+    mut stream: S,
+    input: Result<Output, Vec<u8>>,
+) -> Result<Io, Error<S::Error>> {
+    let Err(buffer) = input else {
+        return Err(Error::MissingInput("write bytes"));
+    };
+
+    debug!("writing bytes asynchronously");
+    let bytes_count = loop {
+        match stream.write(&buffer).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("write interrupted, retrying")
+            }
+            Err(err) => return Err(Error::Stream(err, Io::Write(Err(buffer)))),
+        }
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Write(Ok(output)))
+}
+
+pub async fn write_vectored<S: Write>(
+    mut stream: S,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error<S::Error>> {
+    let Err(buffers) = input else {
+        return Err(Error::MissingInput("write buffers"));
+    };
+
+    debug!("writing first buffer asynchronously");
+    let Some(buffer) = buffers.iter().find(|buffer| !buffer.is_empty()) else {
+        let output = VectoredOutput {
+            buffers,
+            bytes_count: 0,
+        };
+
+        return Ok(Io::WriteVectored(Ok(output)));
+    };
+
+    let bytes_count = loop {
+        match stream.write(buffer).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("write interrupted, retrying")
+            }
+            Err(err) => return Err(Error::Stream(err, Io::WriteVectored(Err(buffers)))),
+        }
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Io::WriteVectored(Ok(output)))
+}
+
+pub async fn flush<S: Write>(mut stream: S, input: Result<(), ()>) -> Result<Io, Error<S::Error>> {
+    let Err(()) = input else {
+        return Err(Error::MissingInput("flush request"));
+    };
+
+    debug!("flushing stream asynchronously");
+    loop {
+        match stream.flush().await {
+            Ok(()) => break,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("flush interrupted, retrying")
+            }
+            Err(err) => return Err(Error::Stream(err, Io::Flush(Err(())))),
+        }
+    }
+
+    Ok(Io::Flush(Ok(())))
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_io_async::ErrorKind;
+
+    use crate::{
+        coroutines::{Flush, ReadLine, WriteAll},
+        runtimes::fixtures::Uart,
+    };
+
+    use super::{handle, run, Error};
+
+    #[tokio::test]
+    async fn exchange() {
+        let mut uart = Uart {
+            rx: b"OK\r\n",
+            tx: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        run(&mut uart, WriteAll::new(*b"AT\r\n")).await.unwrap();
+        run(&mut uart, Flush::new()).await.unwrap();
+        let line = run(&mut uart, ReadLine::new()).await.unwrap();
+
+        assert_eq!(uart.tx, b"AT\r\n");
+        assert_eq!(line, b"OK");
+    }
+
+    #[tokio::test]
+    async fn retry_and_give_back() {
+        let mut uart = Uart {
+            rx: b"",
+            tx: Vec::new(),
+            errors: vec![ErrorKind::Other, ErrorKind::Interrupted],
+        };
+
+        let mut write = WriteAll::new(*b"AT\r\n");
+        let io = write.resume(None).unwrap_err();
+        let err = handle(&mut uart, io.clone()).await.unwrap_err();
+        assert_eq!(err, Error::Stream(ErrorKind::Other, io));
+
+        let arg = handle(&mut uart, err.into_io().unwrap()).await.unwrap();
+        assert_eq!(write.resume(Some(arg)), Ok(4));
+        assert_eq!(uart.tx, b"AT\r\n");
+    }
+}
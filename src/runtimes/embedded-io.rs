@@ -0,0 +1,269 @@
+//! Module dedicated to the [`embedded_io`]-based, blocking runtime.
+//!
+//! This runtime does not depend on the standard library, which makes
+//! it suitable for embedded targets (UART, smoltcp sockets…).
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use embedded_io::{ErrorKind, Read, Write};
+use log::debug;
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+/// The embedded runtime error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error<E> {
+    /// The coroutine emitted an error.
+    Coroutine(crate::Error),
+
+    /// The runtime received an I/O response instead of a request.
+    MissingInput(&'static str),
+
+    /// The runtime does not support the I/O request.
+    Unsupported(&'static str),
+
+    /// The stream failed to process the I/O request, which is given
+    /// back untouched so that it can be processed again.
+    Stream(E, Io),
+}
+
+impl<E> Error<E> {
+    /// Returns the request given back by the error, if any.
+    pub fn io(&self) -> Option<&Io> {
+        match self {
+            Self::Stream(_, io) => Some(io),
+            _ => None,
+        }
+    }
+
+    /// Consumes the error and returns the request it gives back, if
+    /// any.
+    pub fn into_io(self) -> Option<Io> {
+        match self {
+            Self::Stream(_, io) => Some(io),
+            _ => None,
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Coroutine(err) => write!(f, "{err}"),
+            Self::MissingInput(input) => write!(f, "missing {input}"),
+            Self::Unsupported(request) => write!(f, "unsupported {request}"),
+            Self::Stream(err, _) => write!(f, "stream error: {err:?}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug> core::error::Error for Error<E> {}
+
+impl<E: embedded_io::Error> embedded_io::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Coroutine(_) => ErrorKind::Other,
+            Self::MissingInput(_) => ErrorKind::InvalidInput,
+            Self::Unsupported(_) => ErrorKind::Unsupported,
+            Self::Stream(err, _) => err.kind(),
+        }
+    }
+}
+
+/// The main runtime I/O handler.
+///
+/// This handler makes use of [`embedded_io`] traits to process stream
+/// [`Io`]. Interrupted operations are retried, and the request is
+/// given back with any other stream error (see [`Error::Stream`]).
+///
+/// Embedded streams do not expose a way to shut down their write
+/// half nor to write multiple buffers at once, so [`Io::Shutdown`]
+/// is not supported and [`Io::WriteVectored`] only writes the first
+/// buffer. There is no timer either, so [`Io::Timeout`] is not
+/// supported.
+pub fn handle<S>(stream: S, io: Io) -> Result<Io, Error<S::Error>>
+where
+    S: Read + Write,
+{
+    match io {
+        Io::Error(err) => Err(Error::Coroutine(err)),
+        Io::Read(io) => read(stream, io),
+        Io::Write(io) => write(stream, io),
+        Io::WriteVectored(io) => write_vectored(stream, io),
+        Io::Flush(io) => flush(stream, io),
+        Io::Shutdown(_) => Err(Error::Unsupported("shutdown request")),
+        Io::Timeout(_) => Err(Error::Unsupported("timeout request")),
+        Io::Select(_) => Err(Error::Unsupported("select request")),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub fn run<S, C>(mut stream: S, mut coroutine: C) -> Result<C::Output, Error<S::Error>>
+where
+    S: Read + Write,
+    C: Coroutine,
+{
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(&mut stream, io)?),
+        }
+    }
+}
+
+pub fn read<S: Read>(mut stream: S, input: Result<Output, Vec<u8>>) -> Result<Io, Error<S::Error>> {
+    let Err(mut buffer) = input else {
+        return Err(Error::MissingInput("read buffer"));
+    };
+
+    debug!("reading bytes synchronously");
+    let bytes_count = match retry(|| stream.read(&mut buffer)) {
+        Ok(n) => n,
+        Err(err) => return Err(Error::Stream(err, Io::Read(Err(buffer)))),
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Read(Ok(output)))
+}
+
+pub fn write<S: Write>(
+    mut stream: S,
+    input: Result<Output, Vec<u8>>,
+) -> Result<Io, Error<S::Error>> {
+    let Err(buffer) = input else {
+        return Err(Error::MissingInput("write bytes"));
+    };
+
+    debug!("writing bytes synchronously");
+    let bytes_count = match retry(|| stream.write(&buffer)) {
+        Ok(n) => n,
+        Err(err) => return Err(Error::Stream(err, Io::Write(Err(buffer)))),
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Write(Ok(output)))
+}
+
+pub fn write_vectored<S: Write>(
+    mut stream: S,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error<S::Error>> {
+    let Err(buffers) = input else {
+        return Err(Error::MissingInput("write buffers"));
+    };
+
+    debug!("writing first buffer synchronously");
+    let written = match buffers.iter().find(|buffer| !buffer.is_empty()) {
+        Some(buffer) => retry(|| stream.write(buffer)),
+        None => Ok(0),
+    };
+
+    let bytes_count = match written {
+        Ok(n) => n,
+        Err(err) => return Err(Error::Stream(err, Io::WriteVectored(Err(buffers)))),
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Io::WriteVectored(Ok(output)))
+}
+
+pub fn flush<S: Write>(mut stream: S, input: Result<(), ()>) -> Result<Io, Error<S::Error>> {
+    let Err(()) = input else {
+        return Err(Error::MissingInput("flush request"));
+    };
+
+    debug!("flushing stream synchronously");
+    if let Err(err) = retry(|| stream.flush()) {
+        return Err(Error::Stream(err, Io::Flush(Err(()))));
+    }
+
+    Ok(Io::Flush(Ok(())))
+}
+
+/// Runs the given stream operation, retrying as long as it is
+/// interrupted.
+fn retry<T, E: embedded_io::Error>(mut op: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+    loop {
+        match op() {
+            Err(err) if err.kind() == ErrorKind::Interrupted => {
+                debug!("operation interrupted, retrying");
+            }
+            output => break output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_io::ErrorKind;
+
+    use crate::{
+        coroutines::{ReadLine, WriteAll, WriteVectored},
+        runtimes::fixtures::Uart,
+    };
+
+    use super::{handle, run, Error};
+
+    #[test]
+    fn exchange() {
+        let mut uart = Uart {
+            rx: b"OK\r\n",
+            tx: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        run(&mut uart, WriteAll::new(*b"AT")).unwrap();
+        run(
+            &mut uart,
+            WriteVectored::new([b"+GMR".to_vec(), b"\r\n".to_vec()]),
+        )
+        .unwrap();
+        let line = run(&mut uart, ReadLine::new()).unwrap();
+
+        assert_eq!(uart.tx, b"AT+GMR\r\n");
+        assert_eq!(line, b"OK");
+    }
+
+    #[test]
+    fn retry_and_give_back() {
+        let mut uart = Uart {
+            rx: b"OK\r\n",
+            tx: Vec::new(),
+            errors: vec![ErrorKind::Other, ErrorKind::Interrupted],
+        };
+
+        let mut read = ReadLine::new();
+        let io = read.resume(None).unwrap_err();
+        let err = handle(&mut uart, io.clone()).unwrap_err();
+        assert_eq!(err, Error::Stream(ErrorKind::Other, io));
+
+        let mut arg = Some(handle(&mut uart, err.into_io().unwrap()).unwrap());
+
+        let line = loop {
+            match read.resume(arg.take()) {
+                Ok(line) => break line,
+                Err(io) => arg = Some(handle(&mut uart, io).unwrap()),
+            }
+        };
+
+        assert_eq!(line, b"OK");
+    }
+}
@@ -0,0 +1,105 @@
+//! Module dedicated to the runtime [`Error`].
+
+use std::{error, fmt, io};
+
+use log::debug;
+
+use crate::Io;
+
+/// The runtime error, emitted when a stream fails to process an
+/// [`Io`] request.
+///
+/// Unlike a bare [`io::Error`], it gives back the request that could
+/// not be processed, together with its buffer, so that the coroutine
+/// can be resumed once the stream recovered. It converts into an
+/// [`io::Error`], so it can be propagated with `?` as usual.
+#[derive(Debug)]
+pub struct Error {
+    io: Option<Io>,
+    err: io::Error,
+}
+
+impl Error {
+    /// Creates a new runtime error, giving back the given request.
+    pub fn new(io: Io, err: io::Error) -> Self {
+        Self { io: Some(io), err }
+    }
+
+    /// Returns the kind of the inner I/O error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.err.kind()
+    }
+
+    /// Returns the request that could not be processed, if any.
+    ///
+    /// There is no request when the coroutine emitted an error, or
+    /// when the runtime received a response instead of a request.
+    /// Some handlers give back the response instead, when the request
+    /// was processed but a follow-up operation failed.
+    pub fn io(&self) -> Option<&Io> {
+        self.io.as_ref()
+    }
+
+    /// Consumes the error and returns the request that could not be
+    /// processed, if any.
+    pub fn into_io(self) -> Option<Io> {
+        self.io
+    }
+
+    /// Consumes the error and returns both the request that could
+    /// not be processed and the inner I/O error.
+    pub fn into_parts(self) -> (Option<Io>, io::Error) {
+        (self.io, self.err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.err.fmt(f)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.err.source()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self { io: None, err }
+    }
+}
+
+impl From<crate::Error> for Error {
+    fn from(err: crate::Error) -> Self {
+        Self::from(io::Error::from(err))
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        err.err
+    }
+}
+
+/// Runs the given I/O operation, retrying as long as it is
+/// interrupted.
+pub(crate) fn retry<T>(mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match op() {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                debug!("operation interrupted, retrying");
+            }
+            res => break res,
+        }
+    }
+}
+
+/// Builds the error of runtimes unable to process the given request,
+/// giving it back.
+pub(crate) fn unsupported(io: Io) -> Error {
+    let kind = io::ErrorKind::Unsupported;
+    let err = io::Error::new(kind, "request not supported by this runtime");
+    Error::new(io, err)
+}
@@ -0,0 +1,85 @@
+//! Test fixtures shared by runtimes.
+
+#[cfg(feature = "mio")]
+use std::os::unix::net;
+
+#[cfg(feature = "mio")]
+use mio::net::UnixStream;
+
+/// Returns a non-blocking mio stream, connected to a blocking
+/// standard peer.
+#[cfg(feature = "mio")]
+pub(crate) fn pair() -> (UnixStream, net::UnixStream) {
+    let (stream, peer) = net::UnixStream::pair().unwrap();
+    stream.set_nonblocking(true).unwrap();
+    (UnixStream::from_std(stream), peer)
+}
+
+/// In-memory stream, reading at most 2 bytes at a time, and failing
+/// with the given errors first, last one first.
+#[cfg(feature = "embedded-io")]
+pub(crate) struct Uart {
+    pub(crate) rx: &'static [u8],
+    pub(crate) tx: Vec<u8>,
+    pub(crate) errors: Vec<embedded_io::ErrorKind>,
+}
+
+#[cfg(feature = "embedded-io")]
+impl Uart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, embedded_io::ErrorKind> {
+        if let Some(err) = self.errors.pop() {
+            return Err(err);
+        }
+
+        let n = buf.len().min(self.rx.len()).min(2);
+        buf[..n].copy_from_slice(&self.rx[..n]);
+        self.rx = &self.rx[n..];
+        Ok(n)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, embedded_io::ErrorKind> {
+        if let Some(err) = self.errors.pop() {
+            return Err(err);
+        }
+
+        self.tx.extend(buf);
+        Ok(buf.len())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::ErrorType for Uart {
+    type Error = embedded_io::ErrorKind;
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Read for Uart {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Uart::read(self, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Write for Uart {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Uart::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::Read for Uart {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Uart::read(self, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+impl embedded_io_async::Write for Uart {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Uart::write(self, buf)
+    }
+}
@@ -0,0 +1,216 @@
+//! Module dedicated to the [`futures_io`]-based, async runtime.
+//!
+//! Unlike the Tokio runtime, this runtime is executor-agnostic: it
+//! can be used with smol, async-std or any executor whose streams
+//! implement [`futures_io`] traits.
+
+use std::{
+    future::poll_fn,
+    io::{self, IoSlice},
+    pin::Pin,
+};
+
+use futures_io::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+use super::{error::unsupported, Error};
+
+/// The main runtime I/O handler.
+///
+/// This handler makes use of [`futures_io`] traits as well as
+/// standard module [`std::io`] to process stream [`Io`].
+/// Interrupted operations are retried, and the request is given back
+/// with any other error (see [`Error`]).
+///
+/// This runtime does not depend on any timer, so [`Io::Timeout`] is
+/// not supported.
+pub async fn handle(stream: impl AsyncRead + AsyncWrite + Unpin, io: Io) -> Result<Io, Error> {
+    match io {
+        Io::Error(err) => Err(err.into()),
+        Io::Read(io) => read(stream, io).await,
+        Io::Write(io) => write(stream, io).await,
+        Io::WriteVectored(io) => write_vectored(stream, io).await,
+        Io::Flush(io) => flush(stream, io).await,
+        Io::Shutdown(io) => shutdown(stream, io).await,
+        io @ (Io::Timeout(_) | Io::Select(_)) => Err(unsupported(io)),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub async fn run<C: Coroutine>(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(&mut stream, io).await?),
+        }
+    }
+}
+
+pub async fn read(
+    mut stream: impl AsyncRead + Unpin,
+    input: Result<Output, Vec<u8>>,
+) -> Result<Io, Error> {
+    let Err(mut buffer) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing read buffer").into());
+    };
+
+    debug!("reading bytes asynchronously");
+    let bytes_count = loop {
+        match poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut buffer)).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Read(Err(buffer)), err)),
+        }
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Read(Ok(output)))
+}
+
+pub async fn write(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<Output, Vec<u8>>,
+) -> Result<Io, Error> {
+    let Err(buffer) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write bytes").into());
+    };
+
+    debug!("writing bytes asynchronously");
+    let bytes_count = loop {
+        match poll_fn(|cx| Pin::new(&mut stream).poll_write(cx, &buffer)).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Write(Err(buffer)), err)),
+        }
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Io::Write(Ok(output)))
+}
+
+pub async fn write_vectored(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error> {
+    let Err(buffers) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write buffers").into());
+    };
+
+    debug!("writing buffers asynchronously");
+    let slices: Vec<IoSlice> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = loop {
+        match poll_fn(|cx| Pin::new(&mut stream).poll_write_vectored(cx, &slices)).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::WriteVectored(Err(buffers)), err)),
+        }
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Io::WriteVectored(Ok(output)))
+}
+
+pub async fn flush(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<(), ()>,
+) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing flush request").into());
+    };
+
+    debug!("flushing stream asynchronously");
+    loop {
+        match poll_fn(|cx| Pin::new(&mut stream).poll_flush(cx)).await {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Flush(Err(())), err)),
+        }
+    }
+
+    Ok(Io::Flush(Ok(())))
+}
+
+pub async fn shutdown(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<(), ()>,
+) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    debug!("closing stream asynchronously");
+    loop {
+        match poll_fn(|cx| Pin::new(&mut stream).poll_close(cx)).await {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Shutdown(Err(())), err)),
+        }
+    }
+
+    Ok(Io::Shutdown(Ok(())))
+}
+
+#[cfg(test)]
+mod tests {
+    use ::futures::{executor::block_on, io::Cursor};
+
+    use crate::coroutines::{Flush, ReadLine, ReadToEnd, Shutdown, WriteAll, WriteVectored};
+
+    use super::run;
+
+    #[test]
+    fn run_read() {
+        let mut stream = Cursor::new(b"line\nrest".to_vec());
+
+        let line = block_on(run(&mut stream, ReadLine::new())).unwrap();
+        assert_eq!(line, b"line");
+
+        let mut stream = Cursor::new(b"to the end".to_vec());
+
+        let bytes = block_on(run(&mut stream, ReadToEnd::new())).unwrap();
+        assert_eq!(bytes, b"to the end");
+    }
+
+    #[test]
+    fn run_write() {
+        let mut stream = Cursor::new(Vec::new());
+
+        block_on(async {
+            run(&mut stream, WriteAll::new(*b"request")).await?;
+            let buffers = [b" ".to_vec(), b"vectored".to_vec()];
+            run(&mut stream, WriteVectored::new(buffers)).await?;
+            run(&mut stream, Flush::new()).await?;
+            run(&mut stream, Shutdown::new()).await
+        })
+        .unwrap();
+
+        assert_eq!(stream.into_inner(), b"request vectored");
+    }
+}
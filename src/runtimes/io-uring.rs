@@ -0,0 +1,754 @@
+//! Module dedicated to the [`io_uring`]-based, completion runtime.
+//!
+//! Coroutines hand ownership of their buffers to the runtime, which
+//! submits them to the kernel as is and gives them back filled, so
+//! no copy is involved. This runtime is only available on Linux.
+//!
+//! The [`handle`] and [`run`] functions process the requests of a
+//! single coroutine, one at a time. The [`Driver`] drives many
+//! coroutines at once: their requests are submitted to the kernel in
+//! a single batch, and completions are routed back to them.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, ErrorKind},
+    mem,
+    os::fd::{AsRawFd, RawFd},
+};
+
+use io_uring::{cqueue, opcode, squeue, types::Fd, IoUring};
+use log::debug;
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+use super::{error::unsupported, Error};
+
+/// The offset telling the kernel to use the current file position,
+/// ignored by non-seekable streams.
+const CURRENT_POSITION: u64 = u64::MAX;
+
+/// The ring used by the runtime.
+///
+/// The ring is owned by the runtime, so that no other entry can be
+/// submitted to it. Entries are tagged with a unique user data, and
+/// completions not matching the submitted entry are skipped.
+///
+/// If the ring fails while an entry is still queued, the ring is
+/// poisoned: the entry is never submitted, and every later request
+/// fails.
+pub struct Ring {
+    ring: IoUring,
+    user_data: u64,
+    poisoned: bool,
+}
+
+/// The failure of a submission.
+enum Failure {
+    /// The entry never reached the kernel, the memory it references
+    /// can be released.
+    Rejected(io::Error),
+
+    /// The entry may still be processed by the kernel, the memory it
+    /// references must never be released.
+    Lost(io::Error),
+}
+
+impl Ring {
+    /// Creates a new ring with the given amount of submission queue
+    /// entries.
+    pub fn new(entries: u32) -> io::Result<Self> {
+        Ok(Self {
+            ring: IoUring::new(entries)?,
+            user_data: 0,
+            poisoned: false,
+        })
+    }
+
+    /// Returns `true` if the ring failed with queued entries, and
+    /// cannot be used anymore.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Returns an error if the ring is poisoned.
+    fn check(&self) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other("ring poisoned by a failed submission"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns a new, unique user data.
+    fn next_user_data(&mut self) -> u64 {
+        self.user_data = self.user_data.wrapping_add(1);
+        self.user_data
+    }
+
+    /// Submits the queued entries, and waits for the given amount of
+    /// completions. Interrupted waits are retried.
+    ///
+    /// If the submission fails while entries are still queued, the
+    /// ring is poisoned, so that they are never submitted.
+    fn submit_and_wait(&mut self, want: usize) -> io::Result<()> {
+        loop {
+            match self.ring.submit_and_wait(want) {
+                Ok(_) => break Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    if !self.ring.submission().is_empty() {
+                        debug!("submission failed with queued entries, poisoning ring");
+                        self.poisoned = true;
+                    }
+
+                    break Err(err);
+                }
+            }
+        }
+    }
+
+    /// Submits the given entry, waits for its completion, then
+    /// returns its result. The entry is submitted again as long as it
+    /// is interrupted.
+    ///
+    /// # Safety
+    ///
+    /// Memory referenced by the entry must stay valid until this
+    /// function returns, and forever if it fails with
+    /// [`Failure::Lost`].
+    unsafe fn submit(&mut self, entry: &squeue::Entry) -> Result<usize, Failure> {
+        if let Err(err) = self.check() {
+            return Err(Failure::Rejected(err));
+        }
+
+        loop {
+            let user_data = self.next_user_data();
+            let entry = entry.clone().user_data(user_data);
+
+            if self.ring.submission().push(&entry).is_err() {
+                let err = io::Error::other("submission queue is full");
+                return Err(Failure::Rejected(err));
+            }
+
+            let completion: cqueue::Entry = loop {
+                if let Some(completion) = self.ring.completion().next() {
+                    if completion.user_data() == user_data {
+                        break completion;
+                    }
+
+                    debug!("skipping stale completion {}", completion.user_data());
+                    continue;
+                }
+
+                if let Err(err) = self.submit_and_wait(1) {
+                    return Err(Failure::Lost(err));
+                }
+            };
+
+            match completion.result() {
+                res if res == -libc::EINTR => debug!("operation interrupted, retrying"),
+                res if res < 0 => {
+                    let err = io::Error::from_raw_os_error(-res);
+                    return Err(Failure::Rejected(err));
+                }
+                res => return Ok(res as usize),
+            }
+        }
+    }
+}
+
+/// A request ready to be submitted, owning the memory referenced by
+/// its entry.
+struct Op {
+    io: Io,
+    // never read, but referenced by vectored write entries
+    _iovecs: Vec<libc::iovec>,
+    entry: squeue::Entry,
+}
+
+impl Op {
+    /// Builds the entry of the given request targeting the given file
+    /// descriptor, or gives back the request if it cannot be
+    /// submitted.
+    fn new(fd: RawFd, mut io: Io) -> Result<Self, Io> {
+        let mut iovecs = Vec::new();
+
+        let entry = match &mut io {
+            Io::Read(Err(buffer)) => {
+                debug!("preparing read of {} bytes", buffer.len());
+                opcode::Read::new(Fd(fd), buffer.as_mut_ptr(), len(buffer.len()))
+                    .offset(CURRENT_POSITION)
+                    .build()
+            }
+            Io::Write(Err(buffer)) => {
+                debug!("preparing write of {} bytes", buffer.len());
+                opcode::Write::new(Fd(fd), buffer.as_ptr(), len(buffer.len()))
+                    .offset(CURRENT_POSITION)
+                    .build()
+            }
+            Io::WriteVectored(Err(buffers)) => {
+                iovecs = buffers
+                    .iter()
+                    .map(|buffer| libc::iovec {
+                        iov_base: buffer.as_ptr() as *mut _,
+                        iov_len: buffer.len(),
+                    })
+                    .collect();
+
+                debug!("preparing vectored write of {} buffers", iovecs.len());
+                opcode::Writev::new(Fd(fd), iovecs.as_ptr(), len(iovecs.len()))
+                    .offset(CURRENT_POSITION)
+                    .build()
+            }
+            Io::Shutdown(Err(())) => {
+                debug!("preparing shutdown");
+                opcode::Shutdown::new(Fd(fd), libc::SHUT_WR).build()
+            }
+            _ => return Err(io),
+        };
+
+        Ok(Self {
+            io,
+            _iovecs: iovecs,
+            entry,
+        })
+    }
+
+    /// Turns the request into its response, once its entry completed
+    /// with the given amount of bytes.
+    fn respond(self, bytes_count: usize) -> Io {
+        match self.io {
+            Io::Read(Err(buffer)) => Io::Read(Ok(Output {
+                buffer,
+                bytes_count,
+            })),
+            Io::Write(Err(buffer)) => Io::Write(Ok(Output {
+                buffer,
+                bytes_count,
+            })),
+            Io::WriteVectored(Err(buffers)) => Io::WriteVectored(Ok(VectoredOutput {
+                buffers,
+                bytes_count,
+            })),
+            Io::Shutdown(Err(())) => Io::Shutdown(Ok(())),
+            io => io,
+        }
+    }
+
+    /// Leaks the request together with its buffers, since the kernel
+    /// may still access them.
+    fn leak(self) {
+        debug!("entry lost in flight, leaking its buffers");
+        mem::forget(self);
+    }
+}
+
+/// The main runtime I/O handler.
+///
+/// This handler submits the given [`Io`] to the given ring, then
+/// waits for its completion. Interrupted operations are submitted
+/// again, and the request is given back with any other error (see
+/// [`Error`]). If the ring fails while an entry is in flight, the
+/// buffers of the request are leaked rather than given back, since
+/// the kernel may still access them.
+///
+/// Streams managed by file descriptors are not buffered in user
+/// space, so [`Io::Flush`] is a no-op. [`Io::Shutdown`] shuts down
+/// the write half of the stream, and therefore only works with
+/// sockets. [`Io::Timeout`] and [`Io::Select`] are not supported.
+pub fn handle(ring: &mut Ring, stream: &impl AsRawFd, io: Io) -> Result<Io, Error> {
+    let fd = stream.as_raw_fd();
+
+    match io {
+        Io::Error(err) => Err(err.into()),
+        Io::Read(io) => read(ring, fd, io),
+        Io::Write(io) => write(ring, fd, io),
+        Io::WriteVectored(io) => write_vectored(ring, fd, io),
+        Io::Flush(io) => flush(io),
+        Io::Shutdown(io) => shutdown(ring, fd, io),
+        io @ (Io::Timeout(_) | Io::Select(_)) => Err(unsupported(io)),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub fn run<C: Coroutine>(
+    ring: &mut Ring,
+    stream: &impl AsRawFd,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(ring, stream, io)?),
+        }
+    }
+}
+
+pub fn read(ring: &mut Ring, fd: RawFd, input: Result<Output, Vec<u8>>) -> Result<Io, Error> {
+    let Err(buffer) = input else {
+        let kind = ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing read buffer").into());
+    };
+
+    process(ring, fd, Io::Read(Err(buffer)))
+}
+
+pub fn write(ring: &mut Ring, fd: RawFd, input: Result<Output, Vec<u8>>) -> Result<Io, Error> {
+    let Err(buffer) = input else {
+        let kind = ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write bytes").into());
+    };
+
+    process(ring, fd, Io::Write(Err(buffer)))
+}
+
+pub fn write_vectored(
+    ring: &mut Ring,
+    fd: RawFd,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error> {
+    let Err(buffers) = input else {
+        let kind = ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write buffers").into());
+    };
+
+    process(ring, fd, Io::WriteVectored(Err(buffers)))
+}
+
+pub fn flush(input: Result<(), ()>) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing flush request").into());
+    };
+
+    debug!("nothing to flush");
+    Ok(Io::Flush(Ok(())))
+}
+
+pub fn shutdown(ring: &mut Ring, fd: RawFd, input: Result<(), ()>) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    process(ring, fd, Io::Shutdown(Err(())))
+}
+
+/// Submits the given request, then waits for its completion.
+///
+/// The request is given back if its entry never reached the kernel,
+/// otherwise it is leaked together with its buffers.
+fn process(ring: &mut Ring, fd: RawFd, io: Io) -> Result<Io, Error> {
+    let op = Op::new(fd, io).map_err(unsupported)?;
+
+    // SAFETY: the op owns the memory referenced by its entry, and
+    // outlives it since the completion is awaited before returning,
+    // or is leaked if it cannot be awaited
+    match unsafe { ring.submit(&op.entry) } {
+        Ok(bytes_count) => Ok(op.respond(bytes_count)),
+        Err(Failure::Rejected(err)) => Err(Error::new(op.io, err)),
+        Err(Failure::Lost(err)) => {
+            op.leak();
+            Err(err.into())
+        }
+    }
+}
+
+/// A terminated coroutine, reported by [`Driver::poll`].
+#[derive(Debug)]
+pub struct Completion<S, C, O> {
+    /// The token the coroutine was inserted with.
+    pub token: usize,
+
+    /// The stream of the coroutine.
+    pub stream: S,
+
+    /// The coroutine, which can be resumed again after an error.
+    pub coroutine: C,
+
+    /// The output of the coroutine, or the error that interrupted
+    /// it. The error gives back the request that failed.
+    pub output: Result<O, Error>,
+}
+
+struct Task<S, C> {
+    stream: S,
+    coroutine: C,
+    arg: Option<Io>,
+    op: Option<Op>,
+}
+
+impl<S: AsRawFd, C: Coroutine> Task<S, C> {
+    /// Resumes the coroutine until it emits a request to submit, or
+    /// until it terminates.
+    fn progress(&mut self) -> Option<Result<C::Output, Error>> {
+        loop {
+            if self.op.is_some() {
+                break None;
+            }
+
+            match self.coroutine.resume(self.arg.take()) {
+                Ok(output) => break Some(Ok(output)),
+                Err(Io::Error(err)) => break Some(Err(err.into())),
+                Err(Io::Flush(io)) => match flush(io) {
+                    Ok(io) => self.arg = Some(io),
+                    Err(err) => break Some(Err(err)),
+                },
+                Err(io) => match Op::new(self.stream.as_raw_fd(), io) {
+                    Ok(op) => self.op = Some(op),
+                    Err(io) => break Some(Err(unsupported(io))),
+                },
+            }
+        }
+    }
+}
+
+/// The batching driver.
+///
+/// The driver owns a set of stream and coroutine pairs, keyed by a
+/// token. On every [`Driver::poll`], the requests of all the ready
+/// coroutines are submitted at once, with a single system call, then
+/// the available completions are routed back to their coroutine using
+/// the user data of their entry.
+///
+/// Requests in flight are owned by the driver. They are leaked rather
+/// than released if the driver is dropped, or if their coroutine is
+/// removed, since the kernel may still access their buffers.
+pub struct Driver<S, C> {
+    ring: Ring,
+    tasks: HashMap<usize, Task<S, C>>,
+    ready: VecDeque<usize>,
+    inflight: HashMap<u64, usize>,
+}
+
+impl<S, C> Driver<S, C>
+where
+    S: AsRawFd,
+    C: Coroutine,
+{
+    /// Creates a new, empty driver, with a ring of the given amount of
+    /// submission queue entries.
+    pub fn new(entries: u32) -> io::Result<Self> {
+        Ok(Self {
+            ring: Ring::new(entries)?,
+            tasks: HashMap::new(),
+            ready: VecDeque::new(),
+            inflight: HashMap::new(),
+        })
+    }
+
+    /// Returns the number of coroutines not terminated yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if there is no coroutine left to drive.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Schedules the given coroutine over the given stream, under the
+    /// given token.
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if the token is
+    /// already in use.
+    pub fn insert(&mut self, token: usize, stream: S, coroutine: C) -> io::Result<()> {
+        if self.tasks.contains_key(&token) {
+            let kind = io::ErrorKind::AlreadyExists;
+            return Err(io::Error::new(kind, "token already in use"));
+        }
+
+        let task = Task {
+            stream,
+            coroutine,
+            arg: None,
+            op: None,
+        };
+
+        debug!("insert coroutine {token}");
+        self.tasks.insert(token, task);
+        self.ready.push_back(token);
+
+        Ok(())
+    }
+
+    /// Returns the stream and the coroutine matching the given token,
+    /// if any.
+    ///
+    /// A request in flight at that time is leaked.
+    pub fn remove(&mut self, token: usize) -> Option<(S, C)> {
+        let mut task = self.tasks.remove(&token)?;
+
+        if let Some(op) = task.op.take() {
+            self.inflight.retain(|_, inflight| *inflight != token);
+            op.leak();
+        }
+
+        debug!("remove coroutine {token}");
+        Some((task.stream, task.coroutine))
+    }
+
+    /// Submits the requests of all the ready coroutines in a single
+    /// batch, then makes the coroutines of the available completions
+    /// progress, and returns the ones that terminated.
+    ///
+    /// If no coroutine terminated, blocks until at least one entry
+    /// completes. The returned list can be empty.
+    pub fn poll(&mut self) -> io::Result<Vec<Completion<S, C, C::Output>>> {
+        self.ring.check()?;
+
+        let mut completions = Vec::new();
+
+        while let Some(token) = self.ready.pop_front() {
+            let Some(task) = self.tasks.get_mut(&token) else {
+                continue;
+            };
+
+            if let Some(output) = task.progress() {
+                debug!("coroutine {token} terminated");
+                completions.push(self.complete(token, output));
+                continue;
+            }
+
+            if let Err(err) = self.push(token) {
+                self.ready.push_front(token);
+                return Err(err);
+            }
+        }
+
+        if self.inflight.is_empty() {
+            return Ok(completions);
+        }
+
+        let want = if completions.is_empty() { 1 } else { 0 };
+        debug!("submitting {} entries", self.ring.ring.submission().len());
+        self.ring.submit_and_wait(want)?;
+
+        let results: Vec<(u64, i32)> = self
+            .ring
+            .ring
+            .completion()
+            .map(|completion| (completion.user_data(), completion.result()))
+            .collect();
+
+        for (user_data, res) in results {
+            let Some(token) = self.inflight.remove(&user_data) else {
+                debug!("skipping stale completion {user_data}");
+                continue;
+            };
+
+            let Some(task) = self.tasks.get_mut(&token) else {
+                continue;
+            };
+
+            if res == -libc::EINTR {
+                debug!("operation of coroutine {token} interrupted, retrying");
+                self.ready.push_back(token);
+                continue;
+            }
+
+            // the op is kept by the task until its completion
+            let op = task.op.take().unwrap();
+
+            if res < 0 {
+                let err = io::Error::from_raw_os_error(-res);
+                let output = Err(Error::new(op.io, err));
+                completions.push(self.complete(token, output));
+                continue;
+            }
+
+            task.arg = Some(op.respond(res as usize));
+            self.ready.push_back(token);
+        }
+
+        Ok(completions)
+    }
+
+    /// Makes all the coroutines progress until they all terminate,
+    /// and returns their completions.
+    pub fn run(&mut self) -> io::Result<Vec<Completion<S, C, C::Output>>> {
+        let mut completions = Vec::new();
+
+        while !self.is_empty() {
+            completions.extend(self.poll()?);
+        }
+
+        Ok(completions)
+    }
+
+    /// Pushes the entry of the given coroutine to the submission
+    /// queue, submitting the queued entries first if the queue is
+    /// full.
+    fn push(&mut self, token: usize) -> io::Result<()> {
+        let user_data = self.ring.next_user_data();
+
+        // the task emitted a request, since it did not terminate
+        let op = self.tasks[&token].op.as_ref().unwrap();
+        let entry = op.entry.clone().user_data(user_data);
+
+        // SAFETY: the op owns the memory referenced by its entry, and
+        // is kept by the task until its completion, or leaked
+        if unsafe { self.ring.ring.submission().push(&entry) }.is_err() {
+            debug!("submission queue full, submitting queued entries");
+            self.ring.submit_and_wait(0)?;
+
+            // SAFETY: same as above
+            if unsafe { self.ring.ring.submission().push(&entry) }.is_err() {
+                return Err(io::Error::other("submission queue is full"));
+            }
+        }
+
+        self.inflight.insert(user_data, token);
+        Ok(())
+    }
+
+    /// Removes the terminated coroutine matching the given token, and
+    /// builds its completion.
+    fn complete(
+        &mut self,
+        token: usize,
+        output: Result<C::Output, Error>,
+    ) -> Completion<S, C, C::Output> {
+        // the token comes from a task
+        let task = self.tasks.remove(&token).unwrap();
+
+        Completion {
+            token,
+            stream: task.stream,
+            coroutine: task.coroutine,
+            output,
+        }
+    }
+}
+
+impl<S, C> Drop for Driver<S, C> {
+    fn drop(&mut self) {
+        for task in self.tasks.values_mut() {
+            if let Some(op) = task.op.take() {
+                op.leak();
+            }
+        }
+    }
+}
+
+/// Converts a buffer length into a submission length, clamped to
+/// `u32::MAX`.
+fn len(len: usize) -> u32 {
+    len.try_into().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read as _, Write as _},
+        os::unix::net::UnixStream,
+        thread,
+    };
+
+    use io_uring::opcode;
+
+    use crate::{
+        coroutines::{ReadExact, Shutdown, WriteAll, WriteVectored},
+        Coroutine,
+    };
+
+    use super::{run, Driver, Ring};
+
+    /// Returns a new ring, or `None` if the kernel does not support
+    /// io_uring.
+    fn ring() -> Option<Ring> {
+        match Ring::new(8) {
+            Ok(ring) => Some(ring),
+            Err(err) => {
+                eprintln!("skipping test, io_uring not supported: {err}");
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn run_read() {
+        let Some(mut ring) = ring() else { return };
+        let (stream, mut peer) = UnixStream::pair().unwrap();
+
+        peer.write_all(b"hello world").unwrap();
+
+        let output = run(&mut ring, &stream, ReadExact::with_capacity(4, 5)).unwrap();
+        assert_eq!(output, b"hello");
+    }
+
+    #[test]
+    fn run_write() {
+        let Some(mut ring) = ring() else { return };
+        let (stream, mut peer) = UnixStream::pair().unwrap();
+
+        run(&mut ring, &stream, WriteAll::new(*b"hello")).unwrap();
+        let write = WriteVectored::new([b" ".to_vec(), b"world".to_vec()]);
+        run(&mut ring, &stream, write).unwrap();
+        run(&mut ring, &stream, Shutdown::new()).unwrap();
+
+        let mut output = Vec::new();
+        peer.read_to_end(&mut output).unwrap();
+        assert_eq!(output, b"hello world");
+    }
+
+    #[test]
+    fn skip_stale_completions() {
+        let Some(mut ring) = ring() else { return };
+        let (stream, mut peer) = UnixStream::pair().unwrap();
+
+        // SAFETY: the entry does not reference any memory
+        let nop = opcode::Nop::new().build().user_data(u64::MAX);
+        unsafe { ring.ring.submission().push(&nop).unwrap() };
+        ring.ring.submit_and_wait(1).unwrap();
+
+        peer.write_all(b"hello").unwrap();
+
+        let output = run(&mut ring, &stream, ReadExact::new(5)).unwrap();
+        assert_eq!(output, b"hello");
+    }
+
+    #[test]
+    fn driver_batch() {
+        if ring().is_none() {
+            return;
+        }
+
+        let mut driver: Driver<UnixStream, Box<dyn Coroutine<Output = Vec<u8>>>> =
+            Driver::new(8).unwrap();
+
+        let (stream_a, mut peer_a) = UnixStream::pair().unwrap();
+        let (stream_b, mut peer_b) = UnixStream::pair().unwrap();
+
+        // the read only completes once the write is in flight too
+        let read = Box::new(ReadExact::new(5));
+        driver.insert(1, stream_a, read).unwrap();
+        let write = Box::new(WriteAll::new(*b"go").map(|n| vec![n as u8]));
+        driver.insert(2, stream_b, write).unwrap();
+
+        let peer = thread::spawn(move || {
+            let mut signal = [0; 2];
+            peer_b.read_exact(&mut signal).unwrap();
+            assert_eq!(&signal, b"go");
+            peer_a.write_all(b"hello").unwrap();
+        });
+
+        let mut completions = driver.run().unwrap();
+        completions.sort_by_key(|completion| completion.token);
+
+        let outputs: Vec<_> = completions
+            .into_iter()
+            .map(|completion| (completion.token, completion.output.unwrap()))
+            .collect();
+
+        assert_eq!(outputs, [(1, b"hello".to_vec()), (2, vec![2])]);
+
+        peer.join().unwrap();
+        assert!(driver.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+//! Module dedicated to the [`mio`]-based, non-blocking runtime.
+//!
+//! Unlike the standard runtime, a stream that is not ready does not
+//! lose the pending [`Io`]: it is given back as [`Progress::Pending`]
+//! so that it can be processed again once the stream is ready.
+
+use std::{
+    io::{self, IoSlice, Read, Write},
+    net,
+};
+
+use log::debug;
+use mio::{event::Source, net::TcpStream, Events, Interest, Poll, Token};
+
+use crate::{Coroutine, Io, Output, VectoredOutput};
+
+use super::{
+    error::{retry, unsupported},
+    Error,
+};
+
+/// The progress of an [`Io`] request processed by the non-blocking
+/// handler.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Progress {
+    /// The request has been processed, and contains its response.
+    Ready(Io),
+
+    /// The stream was not ready, and contains the untouched request.
+    ///
+    /// The request should be processed again once the stream is
+    /// ready for the [`interest`] of the request.
+    Pending(Io),
+}
+
+/// Returns the readiness the given [`Io`] request waits for, or
+/// `None` if it is not a request.
+pub fn interest(io: &Io) -> Option<Interest> {
+    match io {
+        Io::Read(Err(_)) => Some(Interest::READABLE),
+        Io::Write(Err(_))
+        | Io::WriteVectored(Err(_))
+        | Io::Flush(Err(_))
+        | Io::Shutdown(Err(_)) => Some(Interest::WRITABLE),
+        _ => None,
+    }
+}
+
+/// The main runtime I/O handler.
+///
+/// This handler makes use of standard modules [`std::io`] to process
+/// non-blocking stream [`Io`]. A [`io::ErrorKind::WouldBlock`] error
+/// is turned into [`Progress::Pending`], interrupted operations are
+/// retried, and the request is given back with any other error (see
+/// [`Error`]).
+///
+/// Like the standard runtime, [`Io::Shutdown`] is not supported. See
+/// [`handle_tcp`] for a handler able to shut down TCP streams.
+/// Timeouts are left to the poll, so [`Io::Timeout`] is not
+/// supported either.
+pub fn handle(stream: impl Read + Write, io: Io) -> Result<Progress, Error> {
+    match io {
+        Io::Error(err) => Err(err.into()),
+        Io::Read(io) => read(stream, io),
+        Io::Write(io) => write(stream, io),
+        Io::WriteVectored(io) => write_vectored(stream, io),
+        Io::Flush(io) => flush(stream, io),
+        io @ (Io::Shutdown(_) | Io::Timeout(_) | Io::Select(_)) => Err(unsupported(io)),
+    }
+}
+
+/// The TCP runtime I/O handler.
+///
+/// Same as [`handle`], except that [`Io::Shutdown`] shuts down the
+/// write half of the given TCP stream.
+pub fn handle_tcp(stream: &TcpStream, io: Io) -> Result<Progress, Error> {
+    match io {
+        Io::Shutdown(io) => shutdown(stream, io),
+        io => handle(stream, io),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// The stream is registered to the given poll under the given token
+/// for the whole run, then deregistered. Every time the stream is not
+/// ready, the thread blocks on the poll until the next event.
+///
+/// See [`Reactor`] for driving multiple coroutines at once.
+///
+/// [`Reactor`]: super::reactor::Reactor
+pub fn run<S, C>(
+    poll: &mut Poll,
+    token: Token,
+    stream: &mut S,
+    coroutine: C,
+) -> Result<C::Output, Error>
+where
+    S: Read + Write + Source,
+    C: Coroutine,
+{
+    let interests = Interest::READABLE | Interest::WRITABLE;
+    poll.registry().register(stream, token, interests)?;
+    let output = drive(poll, stream, coroutine, |stream, io| handle(stream, io));
+    poll.registry().deregister(stream)?;
+    output
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Same as [`run`], except that every [`Io`] request is processed by
+/// [`handle_tcp`].
+pub fn run_tcp<C: Coroutine>(
+    poll: &mut Poll,
+    token: Token,
+    stream: &mut TcpStream,
+    coroutine: C,
+) -> Result<C::Output, Error> {
+    let interests = Interest::READABLE | Interest::WRITABLE;
+    poll.registry().register(stream, token, interests)?;
+    let output = drive(poll, stream, coroutine, |stream, io| handle_tcp(stream, io));
+    poll.registry().deregister(stream)?;
+    output
+}
+
+fn drive<S, C>(
+    poll: &mut Poll,
+    stream: &mut S,
+    mut coroutine: C,
+    mut handle: impl FnMut(&mut S, Io) -> Result<Progress, Error>,
+) -> Result<C::Output, Error>
+where
+    C: Coroutine,
+{
+    let mut events = Events::with_capacity(8);
+    let mut arg = None;
+
+    loop {
+        let mut io = match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => io,
+        };
+
+        arg = loop {
+            match handle(stream, io)? {
+                Progress::Ready(io) => break Some(io),
+                Progress::Pending(pending) => {
+                    io = pending;
+                    debug!("waiting for stream readiness");
+                    poll.poll(&mut events, None)?;
+                }
+            }
+        };
+    }
+}
+
+pub fn read(mut stream: impl Read, input: Result<Output, Vec<u8>>) -> Result<Progress, Error> {
+    let Err(mut buffer) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing read buffer").into());
+    };
+
+    debug!("reading bytes without blocking");
+    let bytes_count = match retry(|| stream.read(&mut buffer)) {
+        Ok(n) => n,
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+            debug!("stream not ready for reading");
+            return Ok(Progress::Pending(Io::Read(Err(buffer))));
+        }
+        Err(err) => return Err(Error::new(Io::Read(Err(buffer)), err)),
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Progress::Ready(Io::Read(Ok(output))))
+}
+
+pub fn write(mut stream: impl Write, input: Result<Output, Vec<u8>>) -> Result<Progress, Error> {
+    let Err(buffer) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write bytes").into());
+    };
+
+    debug!("writing bytes without blocking");
+    let bytes_count = match retry(|| stream.write(&buffer)) {
+        Ok(n) => n,
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+            debug!("stream not ready for writing");
+            return Ok(Progress::Pending(Io::Write(Err(buffer))));
+        }
+        Err(err) => return Err(Error::new(Io::Write(Err(buffer)), err)),
+    };
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Ok(Progress::Ready(Io::Write(Ok(output))))
+}
+
+pub fn write_vectored(
+    mut stream: impl Write,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Progress, Error> {
+    let Err(buffers) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write buffers").into());
+    };
+
+    debug!("writing buffers without blocking");
+    let slices: Vec<IoSlice> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = match retry(|| stream.write_vectored(&slices)) {
+        Ok(n) => n,
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+            debug!("stream not ready for writing");
+            return Ok(Progress::Pending(Io::WriteVectored(Err(buffers))));
+        }
+        Err(err) => return Err(Error::new(Io::WriteVectored(Err(buffers)), err)),
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Progress::Ready(Io::WriteVectored(Ok(output))))
+}
+
+pub fn flush(mut stream: impl Write, input: Result<(), ()>) -> Result<Progress, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing flush request").into());
+    };
+
+    debug!("flushing stream without blocking");
+    match retry(|| stream.flush()) {
+        Ok(()) => Ok(Progress::Ready(Io::Flush(Ok(())))),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+            debug!("stream not ready for flushing");
+            Ok(Progress::Pending(Io::Flush(Err(()))))
+        }
+        Err(err) => Err(Error::new(Io::Flush(Err(())), err)),
+    }
+}
+
+pub fn shutdown(stream: &TcpStream, input: Result<(), ()>) -> Result<Progress, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    debug!("shutting down TCP stream without blocking");
+    if let Err(err) = stream.shutdown(net::Shutdown::Write) {
+        return Err(Error::new(Io::Shutdown(Err(())), err));
+    }
+
+    Ok(Progress::Ready(Io::Shutdown(Ok(()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read as _, Write as _},
+        net, thread,
+        time::Duration,
+    };
+
+    use mio::{net::TcpStream, Interest, Poll, Token};
+
+    use crate::{
+        coroutines::{ReadExact, Shutdown, WriteAll},
+        runtimes::fixtures::pair,
+        Io,
+    };
+
+    use super::{handle, interest, run, run_tcp, Progress};
+
+    #[test]
+    fn pending_read() {
+        let (mut stream, _peer) = pair();
+
+        let io = Io::Read(Err(vec![0; 4]));
+        assert_eq!(interest(&io), Some(Interest::READABLE));
+
+        let progress = handle(&mut stream, io.clone()).unwrap();
+        assert_eq!(progress, Progress::Pending(io));
+    }
+
+    #[test]
+    fn run_until_ready() {
+        let (mut stream, mut peer) = pair();
+
+        let writer = thread::spawn(move || {
+            peer.write_all(b"hel").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            peer.write_all(b"lo").unwrap();
+        });
+
+        let mut poll = Poll::new().unwrap();
+        let output = run(&mut poll, Token(0), &mut stream, ReadExact::new(5)).unwrap();
+        assert_eq!(output, b"hello");
+
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn run_tcp_shutdown() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut peer, _) = listener.accept().unwrap();
+        let mut poll = Poll::new().unwrap();
+
+        let err = run(&mut poll, Token(0), &mut stream, Shutdown::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        let write = WriteAll::new(*b"hello");
+        run_tcp(&mut poll, Token(0), &mut stream, write).unwrap();
+        run_tcp(&mut poll, Token(0), &mut stream, Shutdown::new()).unwrap();
+
+        let mut output = Vec::new();
+        peer.read_to_end(&mut output).unwrap();
+        assert_eq!(output, b"hello");
+    }
+}
@@ -0,0 +1,509 @@
+//! Module dedicated to the scripted, mock runtime.
+//!
+//! This runtime does not perform any I/O: it processes [`Io`]
+//! requests against a [`MockStream`], a script of expected writes and
+//! canned reads. It is meant to test coroutines deterministically,
+//! without sockets.
+//!
+//! ```
+//! use io_stream::{coroutines::ReadLine, runtimes::mock::{run, MockStream}};
+//!
+//! let mut stream = MockStream::new().read("* O").read("K\r\n");
+//! let line = run(&mut stream, ReadLine::new()).unwrap();
+//!
+//! assert_eq!(line, b"* OK");
+//! assert!(stream.is_done());
+//! ```
+
+use alloc::{boxed::Box, collections::VecDeque, string::String, vec::Vec};
+use core::{fmt, time::Duration};
+
+use log::debug;
+
+use crate::{
+    Coroutine, Io, Output, SelectOutput, SelectRequest, Side, TimeoutOutput, VectoredOutput,
+};
+
+/// A step of a [`MockStream`] script.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// The stream returns the given bytes on the next read.
+    ///
+    /// If the read buffer is smaller than the bytes, the remaining
+    /// bytes are returned on the following read.
+    Read(Vec<u8>),
+
+    /// The stream returns 0 byte on the next read.
+    Eof,
+
+    /// The stream expects the given bytes to be written.
+    ///
+    /// The stream accepts at most the given bytes per write, which
+    /// allows to script short writes. An empty write step makes the
+    /// stream accept 0 byte.
+    Write(Vec<u8>),
+
+    /// The stream expects to be flushed.
+    Flush,
+
+    /// The stream expects to be shut down.
+    Shutdown,
+
+    /// The stream fails to process the next request, whatever it is.
+    Error(String),
+
+    /// The stream does not process the next request in time.
+    ///
+    /// The request must be an [`Io::Timeout`]. Other timeout requests
+    /// are processed against the step matching their inner request.
+    Timeout,
+
+    /// The stream takes the given duration to process the next
+    /// request.
+    ///
+    /// An [`Io::Timeout`] request shorter than the delay times out,
+    /// otherwise its inner request is processed against the following
+    /// step, and the delay is reported as the time it took. Delays are
+    /// ignored by other requests.
+    Delay(Duration),
+
+    /// The stream is not ready for the next [`Io::Select`].
+    ///
+    /// The request of the other stream is processed instead, see
+    /// [`handle_select`].
+    Pending,
+}
+
+/// The mock runtime error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The coroutine emitted an error.
+    Coroutine(crate::Error),
+
+    /// The coroutine emitted an I/O that does not match the next step
+    /// of the script (`None` meaning that the script is done).
+    Unexpected { expected: Option<Step>, got: Io },
+
+    /// The script injected an error.
+    Injected(String),
+
+    /// The coroutine emitted a request without targeting any stream,
+    /// while a [`Io::Select`] was expected.
+    Untagged(Io),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Coroutine(err) => write!(f, "{err}"),
+            Self::Unexpected {
+                expected: Some(step),
+                got,
+            } => write!(f, "expected {step:?}, got {got:?}"),
+            Self::Unexpected {
+                expected: None,
+                got,
+            } => write!(f, "expected end of script, got {got:?}"),
+            Self::Injected(reason) => write!(f, "injected error: {reason}"),
+            Self::Untagged(got) => write!(f, "expected select, got {got:?}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// The scripted stream.
+///
+/// The script is built step by step, then consumed in order by
+/// [`handle`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MockStream {
+    steps: VecDeque<Step>,
+}
+
+impl MockStream {
+    /// Creates a new, empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given step to the script.
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push_back(step);
+        self
+    }
+
+    /// Adds a [`Step::Read`] to the script.
+    pub fn read(self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.step(Step::Read(bytes.into()))
+    }
+
+    /// Adds a [`Step::Eof`] to the script.
+    pub fn eof(self) -> Self {
+        self.step(Step::Eof)
+    }
+
+    /// Adds a [`Step::Write`] to the script.
+    pub fn write(self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.step(Step::Write(bytes.into()))
+    }
+
+    /// Adds a [`Step::Flush`] to the script.
+    pub fn flush(self) -> Self {
+        self.step(Step::Flush)
+    }
+
+    /// Adds a [`Step::Shutdown`] to the script.
+    pub fn shutdown(self) -> Self {
+        self.step(Step::Shutdown)
+    }
+
+    /// Adds a [`Step::Timeout`] to the script.
+    pub fn timeout(self) -> Self {
+        self.step(Step::Timeout)
+    }
+
+    /// Adds a [`Step::Delay`] to the script.
+    pub fn delay(self, delay: Duration) -> Self {
+        self.step(Step::Delay(delay))
+    }
+
+    /// Adds a [`Step::Pending`] to the script.
+    pub fn pending(self) -> Self {
+        self.step(Step::Pending)
+    }
+
+    /// Adds a [`Step::Error`] to the script.
+    pub fn error(self, reason: impl Into<String>) -> Self {
+        self.step(Step::Error(reason.into()))
+    }
+
+    /// Returns the steps not consumed yet.
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter()
+    }
+
+    /// Returns `true` if the whole script has been consumed.
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Consumes the next step if it is a [`Step::Pending`].
+    fn take_pending(&mut self) -> bool {
+        let pending = self.steps.front() == Some(&Step::Pending);
+
+        if pending {
+            self.steps.pop_front();
+        }
+
+        pending
+    }
+}
+
+/// The main runtime I/O handler.
+///
+/// This handler processes the given [`Io`] request against the next
+/// step of the given script.
+pub fn handle(stream: &mut MockStream, io: Io) -> Result<Io, Error> {
+    if let Io::Error(err) = io {
+        return Err(Error::Coroutine(err));
+    }
+
+    let Some(step) = stream.steps.pop_front() else {
+        debug!("script done, got unexpected {io:?}");
+        let expected = None;
+        return Err(Error::Unexpected { expected, got: io });
+    };
+
+    match (step, io) {
+        (Step::Error(reason), _) => {
+            debug!("inject error: {reason}");
+            Err(Error::Injected(reason))
+        }
+        (Step::Read(bytes), Io::Read(Err(buffer))) => Ok(read(stream, bytes, buffer)),
+        (Step::Eof, Io::Read(Err(buffer))) => {
+            debug!("read EOF");
+            let output = Output {
+                buffer,
+                bytes_count: 0,
+            };
+            Ok(Io::Read(Ok(output)))
+        }
+        (Step::Write(bytes), Io::Write(Err(buffer))) => match write(stream, bytes, &buffer) {
+            Ok(bytes_count) => {
+                let output = Output {
+                    buffer,
+                    bytes_count,
+                };
+                Ok(Io::Write(Ok(output)))
+            }
+            Err(expected) => {
+                let got = Io::Write(Err(buffer));
+                Err(Error::Unexpected { expected, got })
+            }
+        },
+        (Step::Write(bytes), Io::WriteVectored(Err(buffers))) => {
+            match write(stream, bytes, &buffers.concat()) {
+                Ok(bytes_count) => {
+                    let output = VectoredOutput {
+                        buffers,
+                        bytes_count,
+                    };
+                    Ok(Io::WriteVectored(Ok(output)))
+                }
+                Err(expected) => {
+                    let got = Io::WriteVectored(Err(buffers));
+                    Err(Error::Unexpected { expected, got })
+                }
+            }
+        }
+        (Step::Flush, Io::Flush(Err(()))) => {
+            debug!("flush");
+            Ok(Io::Flush(Ok(())))
+        }
+        (Step::Shutdown, Io::Shutdown(Err(()))) => {
+            debug!("shut down");
+            Ok(Io::Shutdown(Ok(())))
+        }
+        (Step::Timeout, Io::Timeout(Err((_, io)))) => {
+            debug!("time out");
+            Ok(Io::Timeout(Ok(TimeoutOutput::Elapsed(io))))
+        }
+        (Step::Delay(delay), Io::Timeout(Err((duration, io)))) if delay >= duration => {
+            debug!("time out after {duration:?}");
+            Ok(Io::Timeout(Ok(TimeoutOutput::Elapsed(io))))
+        }
+        (Step::Delay(delay), Io::Timeout(Err((_, io)))) => {
+            debug!("delay by {delay:?}");
+            let io = handle(stream, *io)?;
+            Ok(Io::Timeout(Ok(TimeoutOutput::Done(Box::new(io), delay))))
+        }
+        (Step::Delay(delay), io) => {
+            debug!("ignore delay of {delay:?}");
+            handle(stream, io)
+        }
+        (step, Io::Timeout(Err((_, io)))) => {
+            stream.steps.push_front(step);
+            let io = handle(stream, *io)?;
+            let elapsed = Duration::ZERO;
+            Ok(Io::Timeout(Ok(TimeoutOutput::Done(Box::new(io), elapsed))))
+        }
+        (step, io) => {
+            debug!("expected {step:?}, got unexpected {io:?}");
+            let expected = Some(step);
+            Err(Error::Unexpected { expected, got: io })
+        }
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub fn run<C: Coroutine>(stream: &mut MockStream, mut coroutine: C) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(stream, io)?),
+        }
+    }
+}
+
+/// The select runtime I/O handler.
+///
+/// This handler processes the given [`Io::Select`] request against
+/// the scripts of both streams. [`Step::Pending`] steps of streams
+/// with a request are consumed first, then the left request is
+/// processed if its stream is ready, otherwise the right one.
+///
+/// When a stream has both a read and a write request, the read
+/// request is processed if the next step is a [`Step::Read`] or a
+/// [`Step::Eof`], otherwise the write request.
+pub fn handle_select(left: &mut MockStream, right: &mut MockStream, io: Io) -> Result<Io, Error> {
+    let mut pending = match io {
+        Io::Error(err) => return Err(Error::Coroutine(err)),
+        Io::Select(Err(request)) => request,
+        io => return Err(Error::Untagged(io)),
+    };
+
+    let left_targeted = pending.left.is_some() || pending.left_write.is_some();
+    let right_targeted = pending.right.is_some() || pending.right_write.is_some();
+    let left_ready = left_targeted && !left.take_pending();
+    let right_ready = right_targeted && !right.take_pending();
+
+    let (side, stream, read, write) = if left_ready {
+        let SelectRequest {
+            left: read,
+            left_write: write,
+            ..
+        } = &mut pending;
+        (Side::Left, left, read, write)
+    } else if right_ready {
+        let SelectRequest {
+            right: read,
+            right_write: write,
+            ..
+        } = &mut pending;
+        (Side::Right, right, read, write)
+    } else {
+        debug!("no stream ready, got unexpected {pending:?}");
+        let expected = Some(Step::Pending);
+        let got = Io::Select(Err(pending));
+        return Err(Error::Unexpected { expected, got });
+    };
+
+    let reading = matches!(stream.steps.front(), Some(Step::Read(_) | Step::Eof));
+
+    // the ready side has at least one request
+    let io = match write.take() {
+        Some(io) if read.is_none() || !reading => io,
+        io => {
+            *write = io;
+            read.take().unwrap()
+        }
+    };
+
+    debug!("select {side:?} stream");
+    let io = Box::new(handle(stream, *io)?);
+    Ok(Io::Select(Ok(SelectOutput { side, io, pending })))
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle_select`], and its response is used to resume the
+/// coroutine.
+pub fn run_select<C: Coroutine>(
+    left: &mut MockStream,
+    right: &mut MockStream,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle_select(left, right, io)?),
+        }
+    }
+}
+
+fn read(stream: &mut MockStream, mut bytes: Vec<u8>, mut buffer: Vec<u8>) -> Io {
+    let bytes_count = bytes.len().min(buffer.len());
+    buffer[..bytes_count].copy_from_slice(&bytes[..bytes_count]);
+    debug!("read {bytes_count} bytes");
+
+    let remaining = bytes.split_off(bytes_count);
+
+    if !remaining.is_empty() {
+        stream.steps.push_front(Step::Read(remaining));
+    }
+
+    let output = Output {
+        buffer,
+        bytes_count,
+    };
+
+    Io::Read(Ok(output))
+}
+
+/// Consumes the given bytes from the expected write step.
+///
+/// On mismatch, returns the expected step so that the caller can
+/// report it along with the request it actually received.
+fn write(
+    stream: &mut MockStream,
+    mut expected: Vec<u8>,
+    got: &[u8],
+) -> Result<usize, Option<Step>> {
+    let bytes_count = expected.len().min(got.len());
+
+    if expected[..bytes_count] != got[..bytes_count] {
+        debug!("expected write {expected:?}, got {got:?}");
+        return Err(Some(Step::Write(expected)));
+    }
+
+    debug!("write {bytes_count} bytes");
+    let remaining = expected.split_off(bytes_count);
+
+    if !remaining.is_empty() {
+        stream.steps.push_front(Step::Write(remaining));
+    }
+
+    Ok(bytes_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coroutines::{Flush, ReadExact, Shutdown, WriteAll, WriteVectored},
+        Io,
+    };
+
+    use super::{run, Error, MockStream, Step};
+
+    #[test]
+    fn script() {
+        let mut stream = MockStream::new()
+            .write("req")
+            .write("uest")
+            .flush()
+            .read("resp")
+            .read("onse")
+            .shutdown();
+
+        assert_eq!(run(&mut stream, WriteAll::new(*b"request")), Ok(7));
+        assert_eq!(run(&mut stream, Flush::new()), Ok(()));
+
+        let response = run(&mut stream, ReadExact::with_capacity(3, 6));
+        assert_eq!(response, Ok(b"respon".to_vec()));
+        assert_eq!(
+            stream.steps().collect::<Vec<_>>(),
+            [&Step::Read(b"se".to_vec()), &Step::Shutdown]
+        );
+    }
+
+    #[test]
+    fn unexpected() {
+        let mut stream = MockStream::new().write("abc").read("def");
+
+        let err = run(&mut stream, WriteAll::new(*b"abd")).unwrap_err();
+        let expected = Some(Step::Write(b"abc".to_vec()));
+        let got = Io::Write(Err(b"abd".to_vec()));
+        assert_eq!(err, Error::Unexpected { expected, got });
+
+        let mut stream = MockStream::new().write("abc").read("def");
+
+        let write = WriteVectored::new(vec![b"a".to_vec(), b"bd".to_vec()]);
+        let err = run(&mut stream, write).unwrap_err();
+        let expected = Some(Step::Write(b"abc".to_vec()));
+        let got = Io::WriteVectored(Err(vec![b"a".to_vec(), b"bd".to_vec()]));
+        assert_eq!(err, Error::Unexpected { expected, got });
+
+        let err = run(&mut stream, Shutdown::new()).unwrap_err();
+        let expected = Some(Step::Read(b"def".to_vec()));
+        let got = Io::Shutdown(Err(()));
+        assert_eq!(err, Error::Unexpected { expected, got });
+
+        let err = run(&mut stream, Flush::new()).unwrap_err();
+        let got = Io::Flush(Err(()));
+        assert_eq!(
+            err,
+            Error::Unexpected {
+                expected: None,
+                got
+            }
+        );
+    }
+
+    #[test]
+    fn injected() {
+        let mut stream = MockStream::new().read("ab").error("connection reset");
+
+        let err = run(&mut stream, ReadExact::new(4)).unwrap_err();
+        assert_eq!(err, Error::Injected("connection reset".into()));
+        assert!(stream.is_done());
+    }
+}
@@ -10,7 +10,34 @@
 //! [`Io`]: crate::Io
 //! [coroutines]: crate::coroutines
 
+#[cfg(feature = "std")]
+pub mod chaos;
+#[cfg(feature = "embedded-io")]
+#[path = "embedded-io.rs"]
+pub mod embedded_io;
+#[cfg(feature = "embedded-io-async")]
+#[path = "embedded-io-async.rs"]
+pub mod embedded_io_async;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "futures-io")]
+pub mod futures;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+#[path = "io-uring.rs"]
+pub mod io_uring;
+#[cfg(feature = "mio")]
+pub mod mio;
+pub mod mock;
+#[cfg(feature = "mio")]
+pub mod reactor;
+#[cfg(feature = "std")]
+pub mod record;
 #[cfg(feature = "std")]
 pub mod std;
 #[cfg(feature = "tokio")]
 pub mod tokio;
+
+#[cfg(feature = "std")]
+pub use error::Error;
@@ -0,0 +1,401 @@
+//! Module dedicated to the [`Reactor`], a [`mio`]-based event loop
+//! driving many coroutines at once.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use log::debug;
+use mio::{event::Source, Events, Interest, Poll, Token};
+
+use crate::{Coroutine, Io};
+
+use super::{
+    mio::{handle, Progress},
+    Error,
+};
+
+/// A terminated coroutine, reported by [`Reactor::poll`].
+#[derive(Debug)]
+pub struct Completion<S, C, O> {
+    /// The token the coroutine was inserted with.
+    pub token: Token,
+
+    /// The stream, deregistered from the reactor.
+    pub stream: S,
+
+    /// The coroutine, which can be resumed again after an error.
+    pub coroutine: C,
+
+    /// The output of the coroutine, or the error that interrupted
+    /// it. The error gives back the request that failed, if any.
+    pub output: Result<O, Error>,
+
+    /// The outcome of the stream deregistration.
+    pub deregistered: io::Result<()>,
+}
+
+#[derive(Debug)]
+struct Task<S, C> {
+    stream: S,
+    coroutine: C,
+    arg: Option<Io>,
+    pending: Option<Io>,
+}
+
+impl<S: Read + Write, C: Coroutine> Task<S, C> {
+    /// Makes the task progress until its stream is not ready anymore,
+    /// or until the coroutine terminates.
+    fn progress(&mut self) -> Option<Result<C::Output, Error>> {
+        loop {
+            let io = match self.pending.take() {
+                Some(io) => io,
+                None => match self.coroutine.resume(self.arg.take()) {
+                    Ok(output) => break Some(Ok(output)),
+                    Err(io) => io,
+                },
+            };
+
+            match handle(&mut self.stream, io) {
+                Ok(Progress::Ready(io)) => self.arg = Some(io),
+                Ok(Progress::Pending(io)) => {
+                    self.pending = Some(io);
+                    break None;
+                }
+                Err(err) => break Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// The multiplexing event loop.
+///
+/// The reactor owns a set of stream and coroutine pairs, keyed by a
+/// [`Token`]. Streams are polled all at once, and readiness events
+/// are routed to the pending [`Io`] of the matching coroutine.
+///
+/// Coroutines of different types can be driven by the same reactor
+/// using boxed trait objects, like `Box<dyn Coroutine<Output = O>>`.
+#[derive(Debug)]
+pub struct Reactor<S, C> {
+    poll: Poll,
+    events: Events,
+    tasks: HashMap<Token, Task<S, C>>,
+    ready: VecDeque<Token>,
+}
+
+impl<S, C> Reactor<S, C>
+where
+    S: Read + Write + Source,
+    C: Coroutine,
+{
+    /// Creates a new, empty reactor.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            poll: Poll::new()?,
+            events: Events::with_capacity(1024),
+            tasks: HashMap::new(),
+            ready: VecDeque::new(),
+        })
+    }
+
+    /// Returns the number of coroutines not terminated yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if there is no coroutine left to drive.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Registers the given stream, and schedules the given coroutine
+    /// under the given token.
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if the token is
+    /// already in use.
+    pub fn insert(&mut self, token: Token, mut stream: S, coroutine: C) -> io::Result<()> {
+        if self.tasks.contains_key(&token) {
+            let kind = io::ErrorKind::AlreadyExists;
+            return Err(io::Error::new(kind, "token already in use"));
+        }
+
+        let interests = Interest::READABLE | Interest::WRITABLE;
+        self.poll
+            .registry()
+            .register(&mut stream, token, interests)?;
+
+        let task = Task {
+            stream,
+            coroutine,
+            arg: None,
+            pending: None,
+        };
+
+        debug!("insert coroutine {token:?}");
+        self.tasks.insert(token, task);
+        self.ready.push_back(token);
+
+        Ok(())
+    }
+
+    /// Deregisters and returns the stream and the coroutine matching
+    /// the given token, if any.
+    ///
+    /// A request pending at that time is lost.
+    pub fn remove(&mut self, token: Token) -> io::Result<Option<(S, C)>> {
+        let Some(mut task) = self.tasks.remove(&token) else {
+            return Ok(None);
+        };
+
+        debug!("remove coroutine {token:?}");
+        self.poll.registry().deregister(&mut task.stream)?;
+        Ok(Some((task.stream, task.coroutine)))
+    }
+
+    /// Makes all the ready coroutines progress, and returns the ones
+    /// that terminated.
+    ///
+    /// If no coroutine is ready, blocks until a stream receives an
+    /// event or until the given timeout expires. The returned list
+    /// can be empty.
+    pub fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<Vec<Completion<S, C, C::Output>>> {
+        if self.ready.is_empty() && !self.tasks.is_empty() {
+            debug!("waiting for events on {} streams", self.tasks.len());
+            self.poll.poll(&mut self.events, timeout)?;
+            self.ready
+                .extend(self.events.iter().map(|event| event.token()));
+        }
+
+        let mut completions = Vec::new();
+
+        while let Some(token) = self.ready.pop_front() {
+            let Some(task) = self.tasks.get_mut(&token) else {
+                continue;
+            };
+
+            let Some(output) = task.progress() else {
+                continue;
+            };
+
+            debug!("coroutine {token:?} terminated");
+            let Some(mut task) = self.tasks.remove(&token) else {
+                continue;
+            };
+
+            let deregistered = self.poll.registry().deregister(&mut task.stream);
+
+            completions.push(Completion {
+                token,
+                stream: task.stream,
+                coroutine: task.coroutine,
+                output,
+                deregistered,
+            });
+        }
+
+        Ok(completions)
+    }
+
+    /// Makes all the coroutines progress until they all terminate,
+    /// and returns their completions.
+    pub fn run(&mut self) -> io::Result<Vec<Completion<S, C, C::Output>>> {
+        let mut completions = Vec::new();
+
+        while !self.is_empty() {
+            completions.extend(self.poll(None)?);
+        }
+
+        Ok(completions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read, Write},
+        os::unix::net,
+        thread,
+        time::Duration,
+    };
+
+    use mio::{event::Source, net::UnixStream, Interest, Registry, Token};
+
+    use crate::{
+        coroutines::{ReadExact, ReadToEnd, WriteAll},
+        runtimes::{fixtures::pair, std::handle},
+        Coroutine, Io,
+    };
+
+    use super::Reactor;
+
+    #[test]
+    fn multiplex() {
+        let mut reactor: Reactor<UnixStream, Box<dyn Coroutine<Output = Vec<u8>>>> =
+            Reactor::new().unwrap();
+
+        let (stream_a, mut peer_a) = pair();
+        let (stream_b, mut peer_b) = pair();
+        let (stream_c, mut peer_c) = pair();
+
+        let read = Box::new(ReadExact::new(5));
+        reactor.insert(Token(1), stream_a, read).unwrap();
+        let read = Box::new(ReadExact::new(5));
+        reactor.insert(Token(2), stream_b, read).unwrap();
+        let read = Box::new(ReadToEnd::new());
+        reactor.insert(Token(3), stream_c, read).unwrap();
+
+        let peers = thread::spawn(move || {
+            peer_b.write_all(b"world").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            peer_a.write_all(b"hel").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            peer_a.write_all(b"lo").unwrap();
+            peer_c.write_all(b"bye").unwrap();
+        });
+
+        let mut completions = reactor.run().unwrap();
+        completions.sort_by_key(|completion| completion.token);
+
+        let outputs: Vec<_> = completions
+            .into_iter()
+            .map(|completion| (completion.token, completion.output.unwrap()))
+            .collect();
+
+        assert_eq!(
+            outputs,
+            [
+                (Token(1), b"hello".to_vec()),
+                (Token(2), b"world".to_vec()),
+                (Token(3), b"bye".to_vec()),
+            ]
+        );
+
+        peers.join().unwrap();
+        assert!(reactor.is_empty());
+    }
+
+    /// Stream failing to deregister.
+    struct Sticky(UnixStream);
+
+    impl Read for Sticky {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Sticky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    impl Source for Sticky {
+        fn register(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            self.0.register(registry, token, interests)
+        }
+
+        fn reregister(
+            &mut self,
+            registry: &Registry,
+            token: Token,
+            interests: Interest,
+        ) -> io::Result<()> {
+            self.0.reregister(registry, token, interests)
+        }
+
+        fn deregister(&mut self, _: &Registry) -> io::Result<()> {
+            Err(io::Error::other("sticky"))
+        }
+    }
+
+    #[test]
+    fn deregister_failure() {
+        let mut reactor = Reactor::new().unwrap();
+
+        let (stream_a, mut peer_a) = pair();
+        let (stream_b, mut peer_b) = pair();
+        reactor
+            .insert(Token(1), Sticky(stream_a), ReadExact::new(2))
+            .unwrap();
+        reactor
+            .insert(Token(2), Sticky(stream_b), ReadExact::new(2))
+            .unwrap();
+
+        peer_a.write_all(b"ok").unwrap();
+        peer_b.write_all(b"ok").unwrap();
+
+        let mut completions = reactor.run().unwrap();
+        completions.sort_by_key(|completion| completion.token);
+        assert_eq!(completions.len(), 2);
+
+        for completion in completions {
+            assert_eq!(completion.output.unwrap(), b"ok");
+            assert_eq!(completion.deregistered.unwrap_err().to_string(), "sticky");
+        }
+
+        assert!(reactor.is_empty());
+    }
+
+    #[test]
+    fn give_back_on_error() {
+        let mut reactor = Reactor::new().unwrap();
+
+        let (stream, peer) = pair();
+        drop(peer);
+        let write = WriteAll::new(*b"hello");
+        reactor.insert(Token(1), stream, write).unwrap();
+
+        let mut completions = reactor.run().unwrap();
+        let completion = completions.pop().unwrap();
+
+        let io = completion.output.unwrap_err().into_io();
+        assert_eq!(io, Some(Io::Write(Err(b"hello".to_vec()))));
+
+        // the coroutine can be resumed with the given back request
+        let mut write = completion.coroutine;
+        let (mut stream, mut peer) = net::UnixStream::pair().unwrap();
+        let mut arg = Some(handle(&mut stream, io.unwrap()).unwrap());
+
+        let output = loop {
+            match write.resume(arg.take()) {
+                Ok(output) => break output,
+                Err(io) => arg = Some(handle(&mut stream, io).unwrap()),
+            }
+        };
+
+        assert_eq!(output, 5);
+        let mut bytes = [0; 5];
+        peer.read_exact(&mut bytes).unwrap();
+        assert_eq!(&bytes, b"hello");
+    }
+
+    #[test]
+    fn token_in_use() {
+        let mut reactor = Reactor::new().unwrap();
+
+        let (stream, _peer_a) = pair();
+        reactor.insert(Token(0), stream, ReadExact::new(1)).unwrap();
+
+        let (stream, _peer_b) = pair();
+        let err = reactor.insert(Token(0), stream, ReadExact::new(1));
+        assert_eq!(err.unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+
+        assert!(reactor.remove(Token(0)).unwrap().is_some());
+        assert!(reactor.is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+//! Module dedicated to the record-and-replay runtime wrapper.
+//!
+//! The [`Recorder`] wraps any runtime handler, and writes every
+//! exchanged [`Io`] to a transcript. The [`replay`] function turns a
+//! transcript back into a [`MockStream`], so that a recorded session
+//! can be reproduced offline with the [mock] runtime.
+//!
+//! # Transcript format
+//!
+//! A transcript is a UTF-8 text, made of one exchange per line:
+//!
+//! ```text
+//! <elapsed> <direction> <kind> [<payload>]
+//! ```
+//!
+//! - `elapsed` is the amount of microseconds since the recorder was
+//!   created.
+//! - `direction` is `>` for a request emitted by the coroutine, `<`
+//!   for the response of the runtime, and `!` when the runtime failed
+//!   to process the request.
+//! - `kind` and `payload` depend on the request:
+//!
+//! | request          | `>` payload                      | `<` payload               |
+//! |------------------|----------------------------------|---------------------------|
+//! | `read`           | buffer length                    | hex bytes read            |
+//! | `write`          | hex bytes                        | bytes count               |
+//! | `write-vectored` | hex buffers, separated by commas | bytes count               |
+//! | `flush`          |                                  |                           |
+//! | `shutdown`       |                                  |                           |
+//! | `error`          | coroutine error message          |                           |
+//! | `timeout`        | duration µs, then inner request  | elapsed µs, then response |
+//! | `select`         | inner requests                   | side, then inner response |
+//!
+//! A `!` line has no kind, its payload is the runtime error message.
+//! A `< timeout` line without payload means that the duration elapsed
+//! before the inner request was processed. Inner requests of a
+//! `select` are the left, right, left write and right write ones,
+//! separated by `|`, a missing one being `-`. Select
+//! exchanges are recorded, but cannot be replayed.
+//!
+//! ```text
+//! 12 > write 48454c4f
+//! 57 < write 4
+//! 60 > read 1024
+//! 1032 < read 4f4b
+//! ```
+//!
+//! [mock]: super::mock
+
+use std::{
+    fmt::Write as _,
+    future::Future,
+    io::{self, BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::{Coroutine, Io, Side, TimeoutOutput};
+
+use super::{
+    mock::{MockStream, Step},
+    Error,
+};
+
+/// The runtime handler wrapper, recording exchanged [`Io`] to a
+/// transcript.
+///
+/// See the [module documentation](self) for the transcript format.
+#[derive(Debug)]
+pub struct Recorder<W> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Creates a new recorder, writing the transcript to the given
+    /// writer.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            start: Instant::now(),
+        }
+    }
+
+    /// Records the given request, processes it with the given runtime
+    /// handler, then records its response.
+    ///
+    /// Once the request has been processed, a failure to record its
+    /// outcome is only logged: the response or the runtime error is
+    /// returned as is.
+    pub fn handle(
+        &mut self,
+        io: Io,
+        handle: impl FnOnce(Io) -> Result<Io, Error>,
+    ) -> Result<Io, Error> {
+        if let Err(err) = self.record('>', &io) {
+            return Err(Error::new(io, err));
+        }
+
+        let output = handle(io);
+        self.record_output(output)
+    }
+
+    /// Async version of [`Recorder::handle`], for async runtime
+    /// handlers.
+    pub async fn handle_async<F>(
+        &mut self,
+        io: Io,
+        handle: impl FnOnce(Io) -> F,
+    ) -> Result<Io, Error>
+    where
+        F: Future<Output = Result<Io, Error>>,
+    {
+        if let Err(err) = self.record('>', &io) {
+            return Err(Error::new(io, err));
+        }
+
+        let output = handle(io).await;
+        self.record_output(output)
+    }
+
+    /// Records the outcome of a processed request, then returns it as
+    /// is.
+    fn record_output(&mut self, output: Result<Io, Error>) -> Result<Io, Error> {
+        match output {
+            Ok(io) => {
+                if let Err(err) = self.record('<', &io) {
+                    warn!("cannot record response: {err}");
+                }
+
+                Ok(io)
+            }
+            Err(err) => {
+                let elapsed = self.start.elapsed().as_micros();
+
+                if let Err(err) = writeln!(self.writer, "{elapsed} ! {err}") {
+                    warn!("cannot record runtime error: {err}");
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Runs the given coroutine until it terminates.
+    ///
+    /// Every [`Io`] request emitted by the coroutine is processed by
+    /// [`Recorder::handle`], and its response is used to resume the
+    /// coroutine.
+    pub fn run<C: Coroutine>(
+        &mut self,
+        mut coroutine: C,
+        mut handle: impl FnMut(Io) -> Result<Io, Error>,
+    ) -> Result<C::Output, Error> {
+        let mut arg = None;
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                Ok(output) => break Ok(output),
+                Err(io) => arg = Some(self.handle(io, &mut handle)?),
+            }
+        }
+    }
+
+    /// Consumes the recorder and returns the inner writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn record(&mut self, direction: char, io: &Io) -> io::Result<()> {
+        let elapsed = self.start.elapsed().as_micros();
+        writeln!(self.writer, "{elapsed} {direction} {}", line(io))
+    }
+}
+
+/// Formats the given exchange as a transcript line, without its
+/// elapsed time and direction.
+fn line(io: &Io) -> String {
+    match io {
+        Io::Error(err) => format!("error {err}"),
+        Io::Read(Err(buffer)) => format!("read {}", buffer.len()),
+        Io::Read(Ok(output)) => format!("read {}", encode(output.bytes())),
+        Io::Write(Err(buffer)) => format!("write {}", encode(buffer)),
+        Io::Write(Ok(output)) => format!("write {}", output.bytes_count),
+        Io::WriteVectored(Err(buffers)) => {
+            let buffers: Vec<String> = buffers.iter().map(|b| encode(b)).collect();
+            format!("write-vectored {}", buffers.join(","))
+        }
+        Io::WriteVectored(Ok(output)) => format!("write-vectored {}", output.bytes_count),
+        Io::Flush(_) => String::from("flush"),
+        Io::Shutdown(_) => String::from("shutdown"),
+        Io::Timeout(Err((duration, io))) => {
+            format!("timeout {} {}", duration.as_micros(), line(io))
+        }
+        Io::Timeout(Ok(TimeoutOutput::Done(io, elapsed))) => {
+            format!("timeout {} {}", elapsed.as_micros(), line(io))
+        }
+        Io::Timeout(Ok(TimeoutOutput::Elapsed(_))) => String::from("timeout"),
+        Io::Select(Err(request)) => {
+            let line = |io: &Option<Box<Io>>| io.as_deref().map_or(String::from("-"), line);
+            format!(
+                "select {}|{}|{}|{}",
+                line(&request.left),
+                line(&request.right),
+                line(&request.left_write),
+                line(&request.right_write),
+            )
+        }
+        Io::Select(Ok(output)) => match output.side {
+            Side::Left => format!("select left {}", line(&output.io)),
+            Side::Right => format!("select right {}", line(&output.io)),
+        },
+    }
+}
+
+/// Parses the given transcript into a [`MockStream`].
+///
+/// Timestamps are ignored. Reads are replayed with the recorded
+/// bytes, writes are expected to match the recorded bytes and are
+/// accepted in the recorded amount, and runtime failures are replayed
+/// as [`Step::Error`].
+pub fn replay(reader: impl BufRead) -> io::Result<MockStream> {
+    let mut stream = MockStream::new();
+    let mut request: Option<(String, String)> = None;
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = |reason: &str| {
+            let kind = io::ErrorKind::InvalidData;
+            io::Error::new(kind, format!("invalid transcript line {}: {reason}", n + 1))
+        };
+
+        let mut parts = line.splitn(3, ' ').skip(1);
+        let direction = parts.next().ok_or_else(|| invalid("missing direction"))?;
+        let rest = parts.next().unwrap_or_default();
+        let (mut kind, mut payload) = split(rest);
+
+        // timeouts are replayed against the step of their inner
+        // request, or as a timeout step when they elapsed
+        if kind == "timeout" {
+            if direction == "<" && payload.is_empty() {
+                request
+                    .take()
+                    .ok_or_else(|| invalid("response without request"))?;
+                stream = stream.timeout();
+                continue;
+            }
+
+            let (micros, inner) = split(payload);
+
+            // the time taken is replayed as a delay step
+            if direction == "<" {
+                let micros = micros
+                    .parse()
+                    .map_err(|_| invalid("invalid elapsed time"))?;
+
+                if micros > 0 {
+                    stream = stream.delay(Duration::from_micros(micros));
+                }
+            }
+
+            (kind, payload) = split(inner);
+        }
+
+        match direction {
+            ">" => {
+                request = Some((kind.to_owned(), payload.to_owned()));
+            }
+            "!" => {
+                let Some((kind, _)) = request.take() else {
+                    return Err(invalid("error without request"));
+                };
+
+                // coroutine errors are emitted again by the replayed
+                // coroutine, they do not reach the stream
+                if kind != "error" {
+                    let reason = line.splitn(3, ' ').nth(2).unwrap_or_default();
+                    stream = stream.error(reason);
+                }
+            }
+            "<" => {
+                let Some((request, request_payload)) = request.take() else {
+                    return Err(invalid("response without request"));
+                };
+
+                if request != kind {
+                    return Err(invalid("response does not match request"));
+                }
+
+                stream = match kind {
+                    "read" => {
+                        let bytes = decode(payload).ok_or_else(|| invalid("invalid hex"))?;
+
+                        if bytes.is_empty() {
+                            stream.eof()
+                        } else {
+                            stream.read(bytes)
+                        }
+                    }
+                    "write" | "write-vectored" => {
+                        let mut bytes = Vec::new();
+
+                        for buffer in request_payload.split(',') {
+                            let buffer = decode(buffer).ok_or_else(|| invalid("invalid hex"))?;
+                            bytes.extend(buffer);
+                        }
+
+                        let count: usize = payload
+                            .parse()
+                            .map_err(|_| invalid("invalid bytes count"))?;
+
+                        if count > bytes.len() {
+                            return Err(invalid("bytes count exceeds written bytes"));
+                        }
+
+                        bytes.truncate(count);
+                        stream.step(Step::Write(bytes))
+                    }
+                    "flush" => stream.flush(),
+                    "shutdown" => stream.shutdown(),
+                    _ => return Err(invalid("unknown kind")),
+                };
+            }
+            _ => return Err(invalid("unknown direction")),
+        }
+    }
+
+    Ok(stream)
+}
+
+/// Splits the given line part into a kind and its payload.
+fn split(part: &str) -> (&str, &str) {
+    part.split_once(' ').unwrap_or((part, ""))
+}
+
+fn encode(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Cursor, Write},
+        time::Duration,
+    };
+
+    use crate::{
+        coroutines::{Flush, ReadToEnd, WriteAll},
+        runtimes::{
+            mock::{self, Step},
+            std::handle,
+        },
+    };
+
+    use super::{replay, Recorder};
+
+    #[test]
+    fn record_and_replay() {
+        let mut stream = Cursor::new(b"response".to_vec());
+        let mut recorder = Recorder::new(Vec::new());
+
+        let output = recorder
+            .run(ReadToEnd::with_capacity(4), |io| handle(&mut stream, io))
+            .unwrap();
+        assert_eq!(output, b"response");
+
+        let write = WriteAll::new(*b"request");
+        let output = recorder.run(write, |io| handle(&mut stream, io)).unwrap();
+        assert_eq!(output, 7);
+
+        let err = recorder.run(
+            Flush::new(),
+            |_| Err(io::Error::other("broken pipe").into()),
+        );
+        assert!(err.is_err());
+
+        let transcript = String::from_utf8(recorder.into_inner()).unwrap();
+        let lines: Vec<&str> = transcript
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                "> read 4",
+                "< read 72657370",
+                "> read 4",
+                "< read 6f6e7365",
+                "> read 4",
+                "< read ",
+                "> write 72657175657374",
+                "< write 7",
+                "> flush",
+                "! broken pipe",
+            ]
+        );
+
+        let mut stream = replay(transcript.as_bytes()).unwrap();
+
+        let output = mock::run(&mut stream, ReadToEnd::with_capacity(4)).unwrap();
+        assert_eq!(output, b"response");
+
+        let output = mock::run(&mut stream, WriteAll::new(*b"request")).unwrap();
+        assert_eq!(output, 7);
+
+        let err = mock::run(&mut stream, Flush::new()).unwrap_err();
+        assert_eq!(err, mock::Error::Injected("broken pipe".into()));
+        assert!(stream.is_done());
+    }
+
+    /// Writer accepting a limited amount of lines.
+    struct Lines(usize);
+
+    impl Write for Lines {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::other("transcript full"));
+            }
+
+            self.0 -= buf.iter().filter(|&&b| b == b'\n').count();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_failure_keeps_response() {
+        let mut stream = Cursor::new(Vec::new());
+        let mut recorder = Recorder::new(Lines(1));
+
+        let write = WriteAll::new(*b"request");
+        let output = recorder.run(write, |io| handle(&mut stream, io)).unwrap();
+        assert_eq!(output, 7);
+        assert_eq!(stream.into_inner(), b"request");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn record_and_replay_async() {
+        use ::tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+        use crate::{coroutines::ReadLine, runtimes::tokio, Coroutine};
+
+        let (mut stream, mut peer) = duplex(64);
+        let mut recorder = Recorder::new(Vec::new());
+
+        let server = ::tokio::spawn(async move {
+            let mut request = [0; 5];
+            peer.read_exact(&mut request).await.unwrap();
+            assert_eq!(&request, b"ping\n");
+            peer.write_all(b"pong\n").await.unwrap();
+        });
+
+        let mut exchange = WriteAll::new(*b"ping\n")
+            .then(ReadLine::new())
+            .timeout(Duration::from_secs(5));
+        let mut arg = None;
+
+        let output = loop {
+            match exchange.resume(arg.take()) {
+                Ok(output) => break output,
+                Err(io) => {
+                    let stream = &mut stream;
+                    let handle = move |io| tokio::handle(stream, io);
+                    arg = Some(recorder.handle_async(io, handle).await.unwrap());
+                }
+            }
+        };
+
+        assert_eq!(output, b"pong");
+        server.await.unwrap();
+
+        let transcript = recorder.into_inner();
+        let mut stream = replay(transcript.as_slice()).unwrap();
+
+        let exchange = WriteAll::new(*b"ping\n")
+            .then(ReadLine::new())
+            .timeout(Duration::from_secs(5));
+        let output = mock::run(&mut stream, exchange).unwrap();
+        assert_eq!(output, b"pong");
+        assert!(stream.is_done());
+    }
+
+    #[test]
+    fn replay_timeout() {
+        let transcript = "\
+            0 > timeout 30000000 read 4\n\
+            1 < timeout 1000 read 6f6b\n\
+            2 > timeout 29999000 read 4\n\
+            3 < timeout\n";
+
+        let stream = replay(transcript.as_bytes()).unwrap();
+        let steps: Vec<&Step> = stream.steps().collect();
+        let delay = Step::Delay(Duration::from_millis(1));
+        assert_eq!(steps, [&delay, &Step::Read(b"ok".to_vec()), &Step::Timeout]);
+    }
+
+    #[test]
+    fn replay_invalid() {
+        let err = replay("0 < read 00".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,31 +1,161 @@
 //! Module dedicated to the standard, blocking runtime.
 
-use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+use std::{
+    io::{self, IoSlice, Read, Write},
+    net::{self, TcpStream},
+    time::{Duration, Instant},
+};
 
-use log::debug;
+use log::{debug, warn};
 
-use crate::{Io, Output};
+use crate::{Coroutine, Io, Output, TimeoutOutput, VectoredOutput};
+#[cfg(unix)]
+use crate::{SelectOutput, SelectRequest, Side};
+
+use super::{
+    error::{retry, unsupported},
+    Error,
+};
 
 /// The main runtime I/O handler.
 ///
 /// This handler makes use of standard modules [`std::io`] to process
-/// stream [`Io`].
-pub fn handle(stream: impl Read + Write, io: Io) -> io::Result<Io> {
+/// stream [`Io`]. Interrupted operations are retried, and the
+/// request is given back with any other error (see [`Error`]).
+///
+/// Standard streams do not expose a generic way to shut down their
+/// write half nor to bound operations in time, so [`Io::Shutdown`]
+/// and [`Io::Timeout`] are not supported. See [`handle_tcp`] for a
+/// handler supporting both with TCP streams, and [`handle_select`]
+/// for [`Io::Select`].
+pub fn handle(stream: impl Read + Write, io: Io) -> Result<Io, Error> {
     match io {
-        Io::Error(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        Io::Error(err) => Err(err.into()),
         Io::Read(io) => read(stream, io),
         Io::Write(io) => write(stream, io),
+        Io::WriteVectored(io) => write_vectored(stream, io),
+        Io::Flush(io) => flush(stream, io),
+        io @ (Io::Shutdown(_) | Io::Timeout(_) | Io::Select(_)) => Err(unsupported(io)),
+    }
+}
+
+/// The TCP runtime I/O handler.
+///
+/// Same as [`handle`], except that [`Io::Shutdown`] shuts down the
+/// write half of the given TCP stream, and that [`Io::Timeout`] is
+/// supported.
+pub fn handle_tcp(stream: &TcpStream, io: Io) -> Result<Io, Error> {
+    match io {
+        Io::Shutdown(io) => shutdown(stream, io),
+        Io::Timeout(io) => timeout(stream, io),
+        io => handle(stream, io),
+    }
+}
+
+/// The select runtime I/O handler.
+///
+/// This handler processes [`Io::Select`] requests against the given
+/// pair of streams: it waits for the readiness of both streams with
+/// `poll(2)`, then processes the request of the first ready stream
+/// with [`handle`]. The other requests are given back untouched. The
+/// ready stream is switched to non-blocking mode while its request is
+/// processed, so that a write only takes what the stream accepts
+/// without blocking, and a slow peer cannot stall the other stream.
+/// [`Io::Shutdown`] shuts down the write half of the stream with
+/// `shutdown(2)`, and therefore only works with sockets.
+///
+/// Inner [`Io::Timeout`] requests bound the wait for the readiness of
+/// their stream: the poll times out with the shortest one, which is
+/// then responded as elapsed.
+///
+/// Readiness is the one of the file descriptors, so bytes buffered in
+/// user space (by a TLS layer for example) are not taken into
+/// account.
+#[cfg(unix)]
+pub fn handle_select<L, R>(left: &mut L, right: &mut R, io: Io) -> Result<Io, Error>
+where
+    L: Read + Write + AsRawFd,
+    R: Read + Write + AsRawFd,
+{
+    match io {
+        Io::Error(err) => Err(err.into()),
+        Io::Select(io) => select(left, right, io),
+        io => Err(unsupported(io)),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub fn run<C: Coroutine>(
+    mut stream: impl Read + Write,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(&mut stream, io)?),
+        }
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle_tcp`], and its response is used to resume the coroutine.
+pub fn run_tcp<C: Coroutine>(stream: &TcpStream, mut coroutine: C) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle_tcp(stream, io)?),
+        }
     }
 }
 
-pub fn read(mut stream: impl Read, input: Result<Output, Vec<u8>>) -> io::Result<Io> {
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle_select`], and its response is used to resume the
+/// coroutine.
+#[cfg(unix)]
+pub fn run_select<L, R, C>(
+    left: &mut L,
+    right: &mut R,
+    mut coroutine: C,
+) -> Result<C::Output, Error>
+where
+    L: Read + Write + AsRawFd,
+    R: Read + Write + AsRawFd,
+    C: Coroutine,
+{
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle_select(left, right, io)?),
+        }
+    }
+}
+
+pub fn read(mut stream: impl Read, input: Result<Output, Vec<u8>>) -> Result<Io, Error> {
     let Err(mut buffer) = input else {
         let kind = io::ErrorKind::InvalidInput;
-        return Err(io::Error::new(kind, "missing read buffer"));
+        return Err(io::Error::new(kind, "missing read buffer").into());
     };
 
     debug!("reading bytes synchronously");
-    let bytes_count = stream.read(&mut buffer)?;
+    let bytes_count = match retry(|| stream.read(&mut buffer)) {
+        Ok(n) => n,
+        Err(err) => return Err(Error::new(Io::Read(Err(buffer)), err)),
+    };
 
     let output = Output {
         buffer,
@@ -35,14 +165,17 @@ pub fn read(mut stream: impl Read, input: Result<Output, Vec<u8>>) -> io::Result
     Ok(Io::Read(Ok(output)))
 }
 
-pub fn write(mut stream: impl Write, input: Result<Output, Vec<u8>>) -> io::Result<Io> {
+pub fn write(mut stream: impl Write, input: Result<Output, Vec<u8>>) -> Result<Io, Error> {
     let Err(buffer) = input else {
         let kind = io::ErrorKind::InvalidInput;
-        return Err(io::Error::new(kind, "missing write bytes"));
+        return Err(io::Error::new(kind, "missing write bytes").into());
     };
 
     debug!("writing bytes synchronously");
-    let bytes_count = stream.write(&buffer)?;
+    let bytes_count = match retry(|| stream.write(&buffer)) {
+        Ok(n) => n,
+        Err(err) => return Err(Error::new(Io::Write(Err(buffer)), err)),
+    };
 
     let output = Output {
         buffer,
@@ -51,3 +184,624 @@ pub fn write(mut stream: impl Write, input: Result<Output, Vec<u8>>) -> io::Resu
 
     Ok(Io::Write(Ok(output)))
 }
+
+pub fn write_vectored(
+    mut stream: impl Write,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error> {
+    let Err(buffers) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write buffers").into());
+    };
+
+    debug!("writing buffers synchronously");
+    let slices: Vec<IoSlice> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = match retry(|| stream.write_vectored(&slices)) {
+        Ok(n) => n,
+        Err(err) => return Err(Error::new(Io::WriteVectored(Err(buffers)), err)),
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Io::WriteVectored(Ok(output)))
+}
+
+pub fn flush(mut stream: impl Write, input: Result<(), ()>) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing flush request").into());
+    };
+
+    debug!("flushing stream synchronously");
+    if let Err(err) = retry(|| stream.flush()) {
+        return Err(Error::new(Io::Flush(Err(())), err));
+    }
+
+    Ok(Io::Flush(Ok(())))
+}
+
+pub fn shutdown(stream: &TcpStream, input: Result<(), ()>) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    debug!("shutting down TCP stream synchronously");
+    if let Err(err) = stream.shutdown(net::Shutdown::Write) {
+        return Err(Error::new(Io::Shutdown(Err(())), err));
+    }
+
+    Ok(Io::Shutdown(Ok(())))
+}
+
+#[cfg(unix)]
+pub fn select<L, R>(
+    left: &mut L,
+    right: &mut R,
+    input: Result<SelectOutput, SelectRequest>,
+) -> Result<Io, Error>
+where
+    L: Read + Write + AsRawFd,
+    R: Read + Write + AsRawFd,
+{
+    let Err(mut request) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing select request").into());
+    };
+
+    if request.is_empty() {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing select inner request").into());
+    }
+
+    let start = Instant::now();
+    let timeout = request
+        .slots_mut()
+        .into_iter()
+        .filter_map(|(_, slot)| duration(slot))
+        .min();
+
+    loop {
+        let mut fds = [
+            pollfd(
+                left,
+                [request.left.as_deref(), request.left_write.as_deref()],
+            ),
+            pollfd(
+                right,
+                [request.right.as_deref(), request.right_write.as_deref()],
+            ),
+        ];
+
+        debug!("polling streams readiness synchronously");
+        let polled = retry(|| {
+            let timeout = match timeout {
+                Some(timeout) => millis(timeout.saturating_sub(start.elapsed())),
+                None => -1,
+            };
+
+            // SAFETY: the pointer and the length match the array of
+            // file descriptors
+            match unsafe { libc::poll(fds.as_mut_ptr(), 2, timeout) } {
+                n if n < 0 => Err(io::Error::last_os_error()),
+                n => Ok(n),
+            }
+        });
+
+        match polled {
+            Ok(0) => {
+                // the poll timed out, so the shortest timeout elapsed
+                let (side, slot) = request
+                    .slots_mut()
+                    .into_iter()
+                    .find(|(_, slot)| duration(slot) == timeout)
+                    .unwrap();
+
+                let io = match *slot.take().unwrap() {
+                    Io::Timeout(Err((_, io))) => Io::Timeout(Ok(TimeoutOutput::Elapsed(io))),
+                    io => io,
+                };
+
+                debug!("{side:?} request timed out after {:?}", start.elapsed());
+                return Ok(Io::Select(Ok(SelectOutput {
+                    side,
+                    io: Box::new(io),
+                    pending: request,
+                })));
+            }
+            Ok(_) => (),
+            Err(err) => return Err(Error::new(Io::Select(Err(request)), err)),
+        }
+
+        let (side, revents) = if fds[0].revents != 0 {
+            (Side::Left, fds[0].revents)
+        } else {
+            (Side::Right, fds[1].revents)
+        };
+
+        debug!("{side:?} stream ready");
+
+        let (read, write) = match side {
+            Side::Left => (&mut request.left, &mut request.left_write),
+            Side::Right => (&mut request.right, &mut request.right_write),
+        };
+
+        // the ready side has a request, since streams without request
+        // are ignored by the poll
+        let slot = if write.is_some() && (read.is_none() || revents & libc::POLLOUT != 0) {
+            write
+        } else {
+            read
+        };
+
+        let (timeout, io) = match *slot.take().unwrap() {
+            Io::Timeout(Err((timeout, io))) => (Some(timeout), *io),
+            io => (None, io),
+        };
+
+        // the request is processed without blocking, so that a slow
+        // peer cannot stall the other stream
+        let output = match side {
+            Side::Left => handle_nonblocking(&mut *left, io),
+            Side::Right => handle_nonblocking(&mut *right, io),
+        };
+
+        let output = match output {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock && err.io().is_some() => {
+                debug!("{side:?} stream not ready anymore, polling again");
+                let (io, _) = err.into_parts();
+                let io = io.unwrap();
+
+                *slot = Some(Box::new(match timeout {
+                    Some(timeout) => Io::Timeout(Err((timeout, Box::new(io)))),
+                    None => io,
+                }));
+
+                continue;
+            }
+            output => output,
+        };
+
+        // responses of timeout requests are wrapped back
+        let output = match (timeout, output) {
+            (None, output) => output,
+            (Some(_), Ok(io)) => {
+                let io = Box::new(io);
+                Ok(Io::Timeout(Ok(TimeoutOutput::Done(io, start.elapsed()))))
+            }
+            (Some(timeout), Err(err)) => match err.into_parts() {
+                (Some(io), err) => Err(Error::new(Io::Timeout(Err((timeout, Box::new(io)))), err)),
+                (None, err) => Err(err.into()),
+            },
+        };
+
+        break match output {
+            Ok(io) => Ok(Io::Select(Ok(SelectOutput {
+                side,
+                io: Box::new(io),
+                pending: request,
+            }))),
+            Err(err) => match err.into_parts() {
+                (Some(io), err) => {
+                    *slot = Some(Box::new(io));
+                    Err(Error::new(Io::Select(Err(request)), err))
+                }
+                (None, err) => Err(err.into()),
+            },
+        };
+    }
+}
+
+/// Same as [`handle_fd`], except that the file descriptor is switched
+/// to non-blocking mode while processing the request, so that reads
+/// and writes only process what the stream can take right away.
+///
+/// If the previous mode cannot be restored, the error gives back the
+/// response instead of the request.
+#[cfg(unix)]
+fn handle_nonblocking(stream: &mut (impl Read + Write + AsRawFd), io: Io) -> Result<Io, Error> {
+    let fd = stream.as_raw_fd();
+
+    // SAFETY: the file descriptor is owned by the stream, which
+    // outlives the calls
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return Err(Error::new(io, io::Error::last_os_error()));
+    }
+
+    let output = handle_fd(stream, io);
+
+    // SAFETY: same as above
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0 {
+        let err = io::Error::last_os_error();
+
+        return match output {
+            Ok(io) => Err(Error::new(io, err)),
+            Err(output) => {
+                warn!("cannot restore blocking mode: {err}");
+                Err(output)
+            }
+        };
+    }
+
+    output
+}
+
+/// Same as [`handle`], except that [`Io::Shutdown`] shuts down the
+/// write half of the given stream with `shutdown(2)`.
+#[cfg(unix)]
+fn handle_fd(stream: &mut (impl Read + Write + AsRawFd), io: Io) -> Result<Io, Error> {
+    let Io::Shutdown(input) = io else {
+        return handle(stream, io);
+    };
+
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    debug!("shutting down stream synchronously");
+
+    // SAFETY: the file descriptor is owned by the stream, which
+    // outlives the call
+    if unsafe { libc::shutdown(stream.as_raw_fd(), libc::SHUT_WR) } < 0 {
+        let err = io::Error::last_os_error();
+        return Err(Error::new(Io::Shutdown(Err(())), err));
+    }
+
+    Ok(Io::Shutdown(Ok(())))
+}
+
+/// Builds the poll entry of the given stream, waiting for the
+/// readiness of any of the given requests. Streams without request
+/// are ignored.
+#[cfg(unix)]
+fn pollfd(stream: &impl AsRawFd, ios: [Option<&Io>; 2]) -> libc::pollfd {
+    fn events(io: &Io) -> libc::c_short {
+        match io {
+            Io::Read(_) => libc::POLLIN,
+            Io::Timeout(Err((_, io))) => events(io),
+            _ => libc::POLLOUT,
+        }
+    }
+
+    let events = ios
+        .into_iter()
+        .flatten()
+        .fold(0, |acc, io| acc | events(io));
+
+    libc::pollfd {
+        fd: if events == 0 { -1 } else { stream.as_raw_fd() },
+        events,
+        revents: 0,
+    }
+}
+
+/// Returns the duration of the given slot, if it holds a timeout
+/// request.
+#[cfg(unix)]
+fn duration(slot: &Option<Box<Io>>) -> Option<Duration> {
+    match slot.as_deref() {
+        Some(Io::Timeout(Err((duration, _)))) => Some(*duration),
+        _ => None,
+    }
+}
+
+/// Converts the given duration into a poll timeout, in milliseconds
+/// rounded up so that the poll does not return before the deadline.
+#[cfg(unix)]
+fn millis(duration: Duration) -> libc::c_int {
+    duration
+        .as_micros()
+        .div_ceil(1000)
+        .try_into()
+        .unwrap_or(libc::c_int::MAX)
+}
+
+/// Processes the inner request with the given read and write
+/// timeouts, then restores the previous ones.
+///
+/// If the previous timeouts cannot be restored, the error gives back
+/// the response instead of the request, so that the coroutine can
+/// still be resumed.
+pub fn timeout(
+    stream: &TcpStream,
+    input: Result<TimeoutOutput, (Duration, Box<Io>)>,
+) -> Result<Io, Error> {
+    let Err((duration, io)) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing timeout request").into());
+    };
+
+    let timeouts = stream
+        .read_timeout()
+        .and_then(|read| Ok((read, stream.write_timeout()?)))
+        .and_then(|timeouts| {
+            stream.set_read_timeout(Some(duration))?;
+            stream.set_write_timeout(Some(duration))?;
+            Ok(timeouts)
+        });
+
+    let (read_timeout, write_timeout) = match timeouts {
+        Ok(timeouts) => timeouts,
+        Err(err) => return Err(Error::new(Io::Timeout(Err((duration, io))), err)),
+    };
+
+    debug!("processing request within {duration:?}");
+    let start = Instant::now();
+    let output = match handle_tcp(stream, *io) {
+        Ok(io) => {
+            let elapsed = start.elapsed();
+            Ok(Io::Timeout(Ok(TimeoutOutput::Done(Box::new(io), elapsed))))
+        }
+        Err(err) => match err.into_parts() {
+            (Some(io), err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                debug!("request timed out after {duration:?}");
+                Ok(Io::Timeout(Ok(TimeoutOutput::Elapsed(Box::new(io)))))
+            }
+            (Some(io), err) => Err(Error::new(Io::Timeout(Err((duration, Box::new(io)))), err)),
+            (None, err) => Err(err.into()),
+        },
+    };
+
+    let restored = stream
+        .set_read_timeout(read_timeout)
+        .and_then(|()| stream.set_write_timeout(write_timeout));
+
+    match (output, restored) {
+        (output, Ok(())) => output,
+        (Ok(io), Err(err)) => Err(Error::new(io, err)),
+        (Err(output), Err(err)) => {
+            warn!("cannot restore timeouts: {err}");
+            Err(output)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Cursor, Read, Write as _},
+        net::{TcpListener, TcpStream},
+        time::Duration,
+    };
+
+    use crate::{
+        coroutines::{ReadExact, ReadToEnd, Shutdown, Write, WriteAll, WriteVectored},
+        Coroutine, Error, Io,
+    };
+
+    use super::{handle, handle_tcp, run, run_tcp};
+
+    /// Stream failing every other read with the given error kind.
+    struct Flaky {
+        inner: Cursor<Vec<u8>>,
+        kind: io::ErrorKind,
+        fail: bool,
+    }
+
+    impl Read for Flaky {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.fail = !self.fail;
+
+            if self.fail {
+                Err(self.kind.into())
+            } else {
+                self.inner.read(&mut buf[..1])
+            }
+        }
+    }
+
+    impl io::Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn run_read_to_end() {
+        let mut stream = Cursor::new(b"abcdef".to_vec());
+        let output = run(&mut stream, ReadToEnd::with_capacity(4)).unwrap();
+        assert_eq!(output, b"abcdef");
+    }
+
+    #[test]
+    fn run_write() {
+        let mut stream = Cursor::new(Vec::new());
+        let output = run(&mut stream, Write::new(b"abcdef".to_vec())).unwrap();
+        assert_eq!(output.bytes_count, 6);
+        assert_eq!(stream.into_inner(), b"abcdef");
+    }
+
+    #[test]
+    fn run_write_vectored() {
+        let mut stream = Cursor::new(Vec::new());
+        let write = WriteVectored::new([b"abc".to_vec(), b"def".to_vec()]);
+        let output = run(&mut stream, write).unwrap();
+        assert_eq!(output, 6);
+        assert_eq!(stream.into_inner(), b"abcdef");
+    }
+
+    #[test]
+    fn shutdown_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        run(&client, WriteAll::new(b"request".to_vec())).unwrap();
+
+        let err = run(&client, Shutdown::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        run_tcp(&client, Shutdown::new()).unwrap();
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).unwrap();
+        assert_eq!(request, b"request");
+    }
+
+    #[test]
+    fn retry_interrupted() {
+        let mut stream = Flaky {
+            inner: Cursor::new(b"abcdef".to_vec()),
+            kind: io::ErrorKind::Interrupted,
+            fail: false,
+        };
+
+        let output = run(&mut stream, ReadExact::new(4)).unwrap();
+        assert_eq!(output, b"abcd");
+    }
+
+    #[test]
+    fn resume_after_error() {
+        let mut stream = Flaky {
+            inner: Cursor::new(b"abcdef".to_vec()),
+            kind: io::ErrorKind::TimedOut,
+            fail: false,
+        };
+
+        let mut read = ReadExact::new(4);
+        let mut arg = None;
+        let mut errors = 0;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                Ok(output) => break output,
+                Err(io) => match handle(&mut stream, io) {
+                    Ok(io) => arg = Some(io),
+                    Err(err) => {
+                        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+                        let io = err.into_io().unwrap();
+                        assert!(matches!(io, Io::Read(Err(_))));
+                        errors += 1;
+                        arg = Some(handle(&mut stream, io).unwrap());
+                    }
+                },
+            }
+        };
+
+        assert_eq!(output, b"abcd");
+        assert_eq!(errors, 4);
+    }
+
+    #[test]
+    fn timeout_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        let timeout = Duration::from_millis(50);
+
+        let mut read = ReadExact::new(4).timeout(timeout);
+        let mut arg = None;
+
+        let err = loop {
+            match read.resume(arg.take()) {
+                Ok(_) => panic!("read should time out"),
+                Err(Io::Error(err)) => break err,
+                Err(io) => arg = Some(handle_tcp(&client, io).unwrap()),
+            }
+        };
+
+        assert_eq!(err, Error::TimedOut { timeout });
+        assert_eq!(client.read_timeout().unwrap(), None);
+
+        server.write_all(b"abcd").unwrap();
+        let mut arg = None;
+
+        let output = loop {
+            match read.resume(arg.take()) {
+                Ok(output) => break output,
+                Err(io) => arg = Some(handle_tcp(&client, io).unwrap()),
+            }
+        };
+
+        assert_eq!(output, b"abcd");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn select_and_join() {
+        use std::os::unix::net::UnixStream;
+
+        use crate::coroutines::{Either, Join, ReadLine, Select};
+
+        use super::run_select;
+
+        let (mut left, _left_peer) = UnixStream::pair().unwrap();
+        let (mut right, mut right_peer) = UnixStream::pair().unwrap();
+
+        right_peer.write_all(b"pong\n").unwrap();
+        let select = Select::new(ReadLine::new(), ReadLine::new());
+        let output = run_select(&mut left, &mut right, select).unwrap();
+        assert!(matches!(output, Either::Right((_, ref line)) if line == b"pong"));
+
+        let join = Join::new(WriteAll::new(*b"ping\n"), ReadExact::new(4));
+        right_peer.write_all(b"pong").unwrap();
+        let output = run_select(&mut left, &mut right, join).unwrap();
+        assert_eq!(output, (5, b"pong".to_vec()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn select_slow_peer() {
+        use std::{os::unix::net::UnixStream, thread};
+
+        use crate::coroutines::{Either, ReadLine, Select};
+
+        use super::run_select;
+
+        let (mut left, _left_peer) = UnixStream::pair().unwrap();
+        let (mut right, mut right_peer) = UnixStream::pair().unwrap();
+
+        // the left peer never reads, so the write cannot complete
+        let write = WriteAll::new(vec![0; 1 << 20]);
+        let select = Select::new(write, ReadLine::new());
+
+        let peer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            right_peer.write_all(b"pong\n").unwrap();
+        });
+
+        let output = run_select(&mut left, &mut right, select).unwrap();
+        assert!(matches!(output, Either::Right((_, ref line)) if line == b"pong"));
+
+        peer.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn select_timeout_and_flush() {
+        use std::os::unix::net::UnixStream;
+
+        use crate::coroutines::{Flush, Join, ReadLine, Select};
+
+        use super::run_select;
+
+        let (mut left, _left_peer) = UnixStream::pair().unwrap();
+        let (mut right, mut right_peer) = UnixStream::pair().unwrap();
+        let timeout = Duration::from_millis(50);
+
+        let read = ReadLine::new().timeout(timeout);
+        let select = Select::new(read, ReadLine::new());
+        let err = run_select(&mut left, &mut right, select).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        right_peer.write_all(b"pong\n").unwrap();
+        let read = ReadLine::new().timeout(timeout);
+        let join = Join::new(Flush::new(), read);
+        let output = run_select(&mut left, &mut right, join).unwrap();
+        assert_eq!(output, ((), b"pong".to_vec()));
+    }
+}
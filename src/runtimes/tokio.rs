@@ -1,35 +1,120 @@
 //! Module dedicated to the Tokio-based, async runtime.
 
-use std::io;
+use std::{
+    future::{self, Future},
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
 
 use log::debug;
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    time::Instant,
+};
 
-use crate::{Io, Output};
+use crate::{
+    Coroutine, Io, Output, SelectOutput, SelectRequest, Side, TimeoutOutput, VectoredOutput,
+};
+
+use super::{error::unsupported, Error};
 
 /// The main runtime I/O handler.
 ///
 /// This handler makes use of the [`tokio::io`] module as well as
 /// standard module [`std::io`] to process stream [`Io`].
-pub async fn handle(stream: impl AsyncRead + AsyncWrite + Unpin, io: Io) -> io::Result<Io> {
+/// Interrupted operations are retried, and the request is given back
+/// with any other error (see [`Error`]).
+///
+/// [`Io::Timeout`] requires the Tokio time driver to be enabled. See
+/// [`handle_select`] for [`Io::Select`].
+pub async fn handle(stream: impl AsyncRead + AsyncWrite + Unpin, io: Io) -> Result<Io, Error> {
     match io {
-        Io::Error(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        Io::Error(err) => Err(err.into()),
         Io::Read(io) => read(stream, io).await,
         Io::Write(io) => write(stream, io).await,
+        Io::WriteVectored(io) => write_vectored(stream, io).await,
+        Io::Flush(io) => flush(stream, io).await,
+        Io::Shutdown(io) => shutdown(stream, io).await,
+        Io::Timeout(io) => timeout(stream, io).await,
+        io @ Io::Select(_) => Err(unsupported(io)),
+    }
+}
+
+/// The select runtime I/O handler.
+///
+/// This handler processes [`Io::Select`] requests against the given
+/// pair of streams: all inner requests are dispatched concurrently,
+/// and the first one to complete is responded. The other ones are
+/// cancelled, then given back untouched.
+pub async fn handle_select(
+    left: impl AsyncRead + AsyncWrite + Unpin,
+    right: impl AsyncRead + AsyncWrite + Unpin,
+    io: Io,
+) -> Result<Io, Error> {
+    match io {
+        Io::Error(err) => Err(err.into()),
+        Io::Select(io) => select(left, right, io).await,
+        io => Err(unsupported(io)),
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle`], and its response is used to resume the coroutine.
+pub async fn run<C: Coroutine>(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle(&mut stream, io).await?),
+        }
+    }
+}
+
+/// Runs the given coroutine until it terminates.
+///
+/// Every [`Io`] request emitted by the coroutine is processed by
+/// [`handle_select`], and its response is used to resume the
+/// coroutine.
+pub async fn run_select<C: Coroutine>(
+    mut left: impl AsyncRead + AsyncWrite + Unpin,
+    mut right: impl AsyncRead + AsyncWrite + Unpin,
+    mut coroutine: C,
+) -> Result<C::Output, Error> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io) => arg = Some(handle_select(&mut left, &mut right, io).await?),
+        }
     }
 }
 
 pub async fn read(
     mut stream: impl AsyncRead + Unpin,
     input: Result<Output, Vec<u8>>,
-) -> io::Result<Io> {
+) -> Result<Io, Error> {
     let Err(mut buffer) = input else {
         let kind = io::ErrorKind::InvalidInput;
-        return Err(io::Error::new(kind, "missing read buffer"));
+        return Err(io::Error::new(kind, "missing read buffer").into());
     };
 
     debug!("reading bytes synchronously");
-    let bytes_count = stream.read(&mut buffer).await?;
+    let bytes_count = loop {
+        match stream.read(&mut buffer).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Read(Err(buffer)), err)),
+        }
+    };
 
     let output = Output {
         buffer,
@@ -42,14 +127,20 @@ pub async fn read(
 pub async fn write(
     mut stream: impl AsyncWrite + Unpin,
     input: Result<Output, Vec<u8>>,
-) -> io::Result<Io> {
+) -> Result<Io, Error> {
     let Err(buffer) = input else {
         let kind = io::ErrorKind::InvalidInput;
-        return Err(io::Error::new(kind, "missing write bytes"));
+        return Err(io::Error::new(kind, "missing write bytes").into());
     };
 
     debug!("writing bytes asynchronously");
-    let bytes_count = stream.write(&buffer).await?;
+    let bytes_count = loop {
+        match stream.write(&buffer).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Write(Err(buffer)), err)),
+        }
+    };
 
     let output = Output {
         buffer,
@@ -58,3 +149,388 @@ pub async fn write(
 
     Ok(Io::Write(Ok(output)))
 }
+
+pub async fn write_vectored(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<VectoredOutput, Vec<Vec<u8>>>,
+) -> Result<Io, Error> {
+    let Err(buffers) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing write buffers").into());
+    };
+
+    debug!("writing buffers asynchronously");
+    let slices: Vec<IoSlice> = buffers.iter().map(|buffer| IoSlice::new(buffer)).collect();
+    let bytes_count = loop {
+        match stream.write_vectored(&slices).await {
+            Ok(n) => break n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::WriteVectored(Err(buffers)), err)),
+        }
+    };
+
+    let output = VectoredOutput {
+        buffers,
+        bytes_count,
+    };
+
+    Ok(Io::WriteVectored(Ok(output)))
+}
+
+pub async fn flush(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<(), ()>,
+) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing flush request").into());
+    };
+
+    debug!("flushing stream asynchronously");
+    loop {
+        match stream.flush().await {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Flush(Err(())), err)),
+        }
+    }
+
+    Ok(Io::Flush(Ok(())))
+}
+
+pub async fn shutdown(
+    mut stream: impl AsyncWrite + Unpin,
+    input: Result<(), ()>,
+) -> Result<Io, Error> {
+    let Err(()) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing shutdown request").into());
+    };
+
+    debug!("shutting down stream asynchronously");
+    loop {
+        match stream.shutdown().await {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::new(Io::Shutdown(Err(())), err)),
+        }
+    }
+
+    Ok(Io::Shutdown(Ok(())))
+}
+
+/// Processes the inner request within the given duration, using
+/// [`tokio::time::timeout`].
+///
+/// The inner request is processed in place like a [`select`] one, so
+/// that it can be given back untouched if the duration elapsed. Only
+/// read, write, flush and shutdown requests are supported.
+pub async fn timeout(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    input: Result<TimeoutOutput, (Duration, Box<Io>)>,
+) -> Result<Io, Error> {
+    let Err((duration, mut io)) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing timeout request").into());
+    };
+
+    debug!("processing request asynchronously within {duration:?}");
+    let start = Instant::now();
+    let output = future::poll_fn(|cx| dispatch(&mut stream, cx, &mut io));
+
+    match tokio::time::timeout(duration, output).await {
+        Ok(Ok(bytes_count)) => {
+            let io = Box::new(respond(*io, bytes_count));
+            let elapsed = start.elapsed();
+            Ok(Io::Timeout(Ok(TimeoutOutput::Done(io, elapsed))))
+        }
+        Ok(Err(err)) => Err(Error::new(Io::Timeout(Err((duration, io))), err)),
+        Err(_) => {
+            debug!("request timed out after {duration:?}");
+            Ok(Io::Timeout(Ok(TimeoutOutput::Elapsed(io))))
+        }
+    }
+}
+
+pub async fn select(
+    mut left: impl AsyncRead + AsyncWrite + Unpin,
+    mut right: impl AsyncRead + AsyncWrite + Unpin,
+    input: Result<SelectOutput, SelectRequest>,
+) -> Result<Io, Error> {
+    let Err(mut request) = input else {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing select request").into());
+    };
+
+    if request.is_empty() {
+        let kind = io::ErrorKind::InvalidInput;
+        return Err(io::Error::new(kind, "missing select inner request").into());
+    }
+
+    // inner timeout requests are bounded by their own sleep, and
+    // their inner request is dispatched in place
+    let start = Instant::now();
+    let mut sleeps = request.slots_mut().map(|(_, slot)| match slot.as_deref() {
+        Some(Io::Timeout(Err((duration, _)))) => Some(Box::pin(tokio::time::sleep(*duration))),
+        _ => None,
+    });
+
+    debug!("processing first ready request asynchronously");
+    let (index, output) = future::poll_fn(|cx| {
+        let slots = request.slots_mut();
+
+        for (index, ((side, slot), sleep)) in slots.into_iter().zip(&mut sleeps).enumerate() {
+            let Some(io) = slot else {
+                continue;
+            };
+
+            let io = match &mut **io {
+                Io::Timeout(Err((_, io))) => &mut **io,
+                io => io,
+            };
+
+            let output = match side {
+                Side::Left => dispatch(&mut left, cx, io),
+                Side::Right => dispatch(&mut right, cx, io),
+            };
+
+            if let Poll::Ready(output) = output {
+                return Poll::Ready((index, Some(output)));
+            }
+
+            if let Some(sleep) = sleep {
+                if sleep.as_mut().poll(cx).is_ready() {
+                    return Poll::Ready((index, None));
+                }
+            }
+        }
+
+        Poll::Pending
+    })
+    .await;
+
+    // the dispatched slot has a request
+    let (side, slot) = request.slots_mut().into_iter().nth(index).unwrap();
+
+    let io = match (output, *slot.take().unwrap()) {
+        (Some(Err(err)), io) => {
+            *slot = Some(Box::new(io));
+            return Err(Error::new(Io::Select(Err(request)), err));
+        }
+        (None, Io::Timeout(Err((duration, io)))) => {
+            debug!("{side:?} request timed out after {duration:?}");
+            Io::Timeout(Ok(TimeoutOutput::Elapsed(io)))
+        }
+        (Some(Ok(bytes_count)), Io::Timeout(Err((_, io)))) => {
+            let io = Box::new(respond(*io, bytes_count));
+            Io::Timeout(Ok(TimeoutOutput::Done(io, start.elapsed())))
+        }
+        (Some(Ok(bytes_count)), io) => respond(io, bytes_count),
+        // only timeout requests are bounded by a sleep
+        (None, io) => io,
+    };
+
+    let io = Box::new(io);
+    let pending = request;
+    Ok(Io::Select(Ok(SelectOutput { side, io, pending })))
+}
+
+/// Polls the given request in place, and returns the amount of bytes
+/// read or written.
+///
+/// Operations are cancel-safe, which allows the request to be given
+/// back untouched when another request of a select is processed
+/// first, or when a timeout elapsed.
+fn dispatch(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    cx: &mut Context<'_>,
+    io: &mut Io,
+) -> Poll<io::Result<usize>> {
+    let mut stream = Pin::new(stream);
+
+    loop {
+        let output = match io {
+            Io::Read(Err(buffer)) => {
+                let mut buffer = ReadBuf::new(buffer);
+                ready!(stream.as_mut().poll_read(cx, &mut buffer)).map(|()| buffer.filled().len())
+            }
+            Io::Write(Err(buffer)) => ready!(stream.as_mut().poll_write(cx, buffer)),
+            Io::WriteVectored(Err(buffers)) => {
+                let slices: Vec<IoSlice> = buffers.iter().map(|b| IoSlice::new(b)).collect();
+                ready!(stream.as_mut().poll_write_vectored(cx, &slices))
+            }
+            Io::Flush(Err(())) => ready!(stream.as_mut().poll_flush(cx)).map(|()| 0),
+            Io::Shutdown(Err(())) => ready!(stream.as_mut().poll_shutdown(cx)).map(|()| 0),
+            _ => {
+                let kind = io::ErrorKind::Unsupported;
+                let err = io::Error::new(kind, "request not supported by select");
+                return Poll::Ready(Err(err));
+            }
+        };
+
+        match output {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            output => break Poll::Ready(output),
+        }
+    }
+}
+
+/// Turns the given request processed by [`dispatch`] into its
+/// response.
+fn respond(io: Io, bytes_count: usize) -> Io {
+    match io {
+        Io::Read(Err(buffer)) => Io::Read(Ok(Output {
+            buffer,
+            bytes_count,
+        })),
+        Io::Write(Err(buffer)) => Io::Write(Ok(Output {
+            buffer,
+            bytes_count,
+        })),
+        Io::WriteVectored(Err(buffers)) => Io::WriteVectored(Ok(VectoredOutput {
+            buffers,
+            bytes_count,
+        })),
+        Io::Flush(Err(())) => Io::Flush(Ok(())),
+        Io::Shutdown(Err(())) => Io::Shutdown(Ok(())),
+        io => io,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use tokio::{
+        io::AsyncReadExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::{
+        coroutines::{Flush, ReadExact, Shutdown, WriteAll, WriteVectored},
+        Coroutine, Io, TimeoutOutput,
+    };
+
+    use super::{handle, run};
+
+    #[tokio::test]
+    async fn flush_and_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        let request = WriteAll::new(b"request".to_vec());
+        run(&mut client, request).await.unwrap();
+
+        let request = WriteVectored::new([b" ".to_vec(), b"vectored".to_vec()]);
+        run(&mut client, request).await.unwrap();
+        run(&mut client, Flush::new()).await.unwrap();
+        run(&mut client, Shutdown::new()).await.unwrap();
+
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(request, b"request vectored");
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (_server, _) = listener.accept().await.unwrap();
+        let timeout = Duration::from_millis(50);
+
+        let read = ReadExact::new(4).timeout(timeout);
+        let err = run(&mut client, read).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let read = Box::new(Io::Read(Err(vec![0; 34])));
+        let io = handle(&mut client, Io::Timeout(Err((timeout, read.clone()))));
+        assert_eq!(
+            io.await.unwrap(),
+            Io::Timeout(Ok(TimeoutOutput::Elapsed(read)))
+        );
+    }
+
+    #[tokio::test]
+    async fn select_and_join() {
+        use tokio::io::{duplex, AsyncWriteExt};
+
+        use crate::coroutines::{Either, Join, ReadLine, Select};
+
+        use super::run_select;
+
+        let (mut left, _left_peer) = duplex(64);
+        let (mut right, mut right_peer) = duplex(64);
+
+        right_peer.write_all(b"pong\n").await.unwrap();
+        let select = Select::new(ReadLine::new(), ReadLine::new());
+        let output = run_select(&mut left, &mut right, select).await.unwrap();
+        assert!(matches!(output, Either::Right((_, ref line)) if line == b"pong"));
+
+        let join = Join::new(WriteAll::new(*b"ping\n"), ReadExact::new(4));
+        right_peer.write_all(b"pong").await.unwrap();
+        let output = run_select(&mut left, &mut right, join).await.unwrap();
+        assert_eq!(output, (5, b"pong".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn select_timeout_and_flush() {
+        use tokio::io::{duplex, AsyncWriteExt};
+
+        use crate::coroutines::{Join, ReadLine, Select};
+
+        use super::run_select;
+
+        let (mut left, _left_peer) = duplex(64);
+        let (mut right, mut right_peer) = duplex(64);
+        let timeout = Duration::from_millis(50);
+
+        let read = ReadLine::new().timeout(timeout);
+        let select = Select::new(read, ReadLine::new());
+        let err = run_select(&mut left, &mut right, select).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        right_peer.write_all(b"pong\n").await.unwrap();
+        let read = ReadLine::new().timeout(timeout);
+        let join = Join::new(Flush::new(), read);
+        let output = run_select(&mut left, &mut right, join).await.unwrap();
+        assert_eq!(output, ((), b"pong".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn copy_bidirectional() {
+        use tokio::io::{duplex, AsyncWriteExt};
+
+        use crate::coroutines::CopyBidirectional;
+
+        use super::run_select;
+
+        // both peers send more than the copy and the peer buffers can
+        // hold, before reading anything
+        let (mut left, left_peer) = duplex(16);
+        let (mut right, right_peer) = duplex(16);
+
+        let peer = |mut peer: tokio::io::DuplexStream, bytes: &'static [u8]| async move {
+            peer.write_all(bytes).await.unwrap();
+            peer.shutdown().await.unwrap();
+            let mut received = Vec::new();
+            peer.read_to_end(&mut received).await.unwrap();
+            received
+        };
+
+        let left_peer = tokio::spawn(peer(left_peer, &[b'l'; 34]));
+        let right_peer = tokio::spawn(peer(right_peer, &[b'r'; 34]));
+
+        let copy = CopyBidirectional::with_capacity(4);
+        let copy = run_select(&mut left, &mut right, copy);
+        let output = tokio::time::timeout(Duration::from_secs(5), copy).await;
+        assert_eq!(output.unwrap().unwrap(), (34, 34));
+
+        assert_eq!(left_peer.await.unwrap(), [b'r'; 34]);
+        assert_eq!(right_peer.await.unwrap(), [b'l'; 34]);
+    }
+}
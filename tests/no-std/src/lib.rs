@@ -0,0 +1,53 @@
+//! Compile test ensuring that coroutines can be used from a
+//! `#![no_std]` crate, with `alloc` only.
+//!
+//! On CI, this crate is built for a bare-metal target:
+//!
+//! ```text
+//! cargo build -p io-stream-no-std --target thumbv7em-none-eabi
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use io_stream::{
+    coroutines::{LineEnding, ReadExact, ReadLine, WriteAll},
+    Coroutine, Io,
+};
+
+/// Writes the given request, then reads a response line followed by
+/// a body of the given length.
+///
+/// I/O requests are processed by the given handler, which would be a
+/// UART or a socket on embedded targets.
+pub fn exchange(
+    request: &[u8],
+    body_len: usize,
+    mut handle: impl FnMut(Io) -> Io,
+) -> Result<(Vec<u8>, Vec<u8>), Io> {
+    run(WriteAll::new(request.iter().copied()), &mut handle)?;
+
+    let mut read = ReadLine::new().line_ending(LineEnding::Crlf);
+    let line = run(&mut read, &mut handle)?;
+    let body = run(
+        ReadExact::with_reader(read.into_reader(), body_len),
+        &mut handle,
+    )?;
+
+    Ok((line, body))
+}
+
+fn run<C: Coroutine>(mut coroutine: C, handle: &mut impl FnMut(Io) -> Io) -> Result<C::Output, Io> {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg.take()) {
+            Ok(output) => break Ok(output),
+            Err(io @ Io::Error(_)) => break Err(io),
+            Err(io) => arg = Some(handle(io)),
+        }
+    }
+}